@@ -0,0 +1,167 @@
+use std::cell::RefCell;
+use std::io::Write;
+use std::rc::Rc;
+
+use devy::csp::{content_security_policy, externalize_scripts};
+use devy::dom::HtmlFormat;
+use devy::render::{code_block_filter, page_filter};
+use devy::{Page, Template};
+
+use crate::cli::BuildArgs;
+
+pub fn build(args: &BuildArgs, verbose: u8) -> Result<(), Box<dyn std::error::Error>> {
+    let mut pandoc = pandoc::new();
+    pandoc.set_show_cmdline(verbose > 1);
+
+    let errors = Rc::new(RefCell::new(vec![]));
+    if !args.filters.no_code_blocks {
+        code_block_filter(
+            &mut pandoc,
+            errors.clone(),
+            args.filters.options(),
+            args.filters.overrides(),
+            args.filters.keep_going,
+        );
+    }
+
+    let output = args.output_path();
+    let template = page_template(args)?;
+    if args.page.csp && template.is_some() && output.is_none() {
+        return Err(
+            "--csp writes the article's scripts next to it, so it needs an output file".into(),
+        );
+    }
+    if let Some(path) = output.as_ref().filter(|path| is_input(args, path)) {
+        return Err(format!(
+            "{} is an input of the build and would be overwritten, pass another file with -o",
+            path.display()
+        )
+        .into());
+    }
+
+    let page = Rc::new(RefCell::new(Page {
+        title: args.inputs[0]
+            .file_stem()
+            .map_or(String::new(), |stem| stem.to_string_lossy().into_owned()),
+        ..Page::default()
+    }));
+    if template.is_some() {
+        if !args.page.no_utf8_meta {
+            page.borrow_mut().insert_utf8_meta();
+        }
+        page_filter(&mut pandoc, page.clone(), !args.filters.no_head_tags);
+    }
+
+    let html_format = args
+        .filters
+        .options()
+        .with(&args.filters.overrides())
+        .html_format;
+    if html_format == HtmlFormat::Minified {
+        pandoc.add_option(pandoc::PandocOption::NoWrap);
+    }
+
+    pandoc.set_input(pandoc::InputKind::Files(args.inputs.clone()));
+    pandoc.set_output_format(pandoc::OutputFormat::Other(args.format.clone()), vec![]);
+    // Output is written by hand so that a failing article doesn't overwrite the previous build
+    pandoc.set_output(pandoc::OutputKind::Pipe);
+
+    if verbose > 0 {
+        eprintln!(
+            "rendering {} to {}",
+            args.inputs
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            output
+                .as_ref()
+                .map_or("stdout".to_string(), |p| p.display().to_string())
+        );
+    }
+
+    let mut rendered = match pandoc.execute()? {
+        pandoc::PandocOutput::ToBuffer(out) => out.into_bytes(),
+        pandoc::PandocOutput::ToBufferRaw(out) => out,
+        pandoc::PandocOutput::ToFile(_) => unreachable!("pandoc output is piped"),
+    };
+    let mut script = None;
+    let mut policy = None;
+    if let Some(template) = template {
+        let mut page = page.take();
+        page.body = String::from_utf8(rendered)?;
+        if let (true, Some(output)) = (args.page.csp, &output) {
+            let path = output.with_extension("js");
+            let src = path.file_name().unwrap_or_default().to_string_lossy();
+            script = externalize_scripts(&mut page, &src).map(|content| (path.clone(), content));
+            policy = Some(content_security_policy(&page, html_format));
+        }
+        rendered = template.render(&page, html_format).into_bytes();
+    }
+
+    let errors = errors.take();
+    if errors.is_empty() || args.filters.keep_going {
+        if let Some(parent) = output.as_ref().and_then(|path| path.parent()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        if let Some((path, content)) = script {
+            std::fs::write(path, content)?;
+        }
+        match output {
+            Some(path) => std::fs::write(path, rendered)?,
+            None => std::io::stdout().write_all(&rendered)?,
+        }
+        if let Some(policy) = policy {
+            println!("Content-Security-Policy: {}", policy);
+        }
+    }
+
+    if !errors.is_empty() {
+        let sources = read_sources(&args.inputs);
+        let reports: Vec<String> = errors.iter().map(|e| e.report(&sources)).collect();
+        return Err(format!(
+            "{}\n\n{} code block(s) failed to render",
+            reports.join("\n\nerror: "),
+            errors.len()
+        )
+        .into());
+    }
+
+    Ok(())
+}
+
+/// The template HTML articles are placed into, `None` for fragments and other formats.
+fn page_template(args: &BuildArgs) -> Result<Option<Template>, Box<dyn std::error::Error>> {
+    if !args.is_html() || args.page.no_template {
+        return Ok(None);
+    }
+    let Some(path) = &args.page.template else {
+        return Ok(Some(Template::default()));
+    };
+    let source = std::fs::read_to_string(path)
+        .map_err(|e| format!("could not read template {}: {}", path.display(), e))?;
+    let template = Template::parse(&source)
+        .map_err(|e| format!("invalid template {}: {}", path.display(), e))?;
+    Ok(Some(template))
+}
+
+/// Reads the input files as `(name, contents)` pairs for error reporting, skipping unreadable ones.
+fn read_sources(inputs: &[std::path::PathBuf]) -> Vec<(String, String)> {
+    inputs
+        .iter()
+        .filter_map(|path| {
+            let contents = std::fs::read_to_string(path).ok()?;
+            Some((path.display().to_string(), contents))
+        })
+        .collect()
+}
+
+/// Whether `path` names one of the input files, so that writing to it would destroy the article.
+fn is_input(args: &BuildArgs, path: &std::path::Path) -> bool {
+    let Ok(path) = path.canonicalize() else {
+        return false;
+    };
+    args.inputs
+        .iter()
+        .any(|input| input.canonicalize().is_ok_and(|input| input == path))
+}
@@ -0,0 +1,240 @@
+use std::path::PathBuf;
+
+use clap::{ArgAction, Args, Parser, Subcommand};
+use devy::dom::HtmlFormat;
+use devy::{Options, OptionsLayer, Theme};
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "devy",
+    version,
+    about = "Interactive blog engine built on Pandoc"
+)]
+pub struct Cli {
+    /// Print more information about each step, repeat for pandoc command lines
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    pub verbose: u8,
+
+    /// Project configuration file [default: devy.toml in the current directory or its parents]
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Render markdown articles through the devy filters
+    Build(BuildArgs),
+    /// Run as a Pandoc JSON filter, reading the AST on stdin and writing it to stdout
+    Filter(FilterArgs),
+    /// Rebuild articles whenever they or the scripts they load change
+    Watch(WatchArgs),
+    /// Serve the article on localhost, reloading the browser after every rebuild
+    Serve(ServeArgs),
+    /// Render every article of a content directory into a mirrored output directory
+    Site(SiteArgs),
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct FilterToggles {
+    /// Leave code blocks untouched instead of rendering DCL, mermaid and scripts
+    #[arg(long)]
+    pub no_code_blocks: bool,
+
+    /// Do not add the description and Open Graph tags read from the front matter to the head
+    #[arg(long)]
+    pub no_head_tags: bool,
+
+    /// Render failing code blocks as error panels and report every error at the end
+    #[arg(short, long)]
+    pub keep_going: bool,
+
+    /// Frame interval of animated mermaid diagrams, in milliseconds
+    #[arg(long, value_name = "MS")]
+    pub mermaid_rate: Option<f64>,
+
+    /// Script URL mermaid diagrams are rendered with
+    #[arg(long, value_name = "URL")]
+    pub mermaid_cdn: Option<String>,
+
+    /// Show line numbers on every code listing
+    #[arg(long, num_args = 0..=1, default_missing_value = "true", value_name = "BOOL")]
+    pub line_numbers: Option<bool>,
+
+    /// Add a copy button to every code listing
+    #[arg(long, num_args = 0..=1, default_missing_value = "true", value_name = "BOOL")]
+    pub copy: Option<bool>,
+
+    /// Built-in stylesheet of the generated components: auto, light, dark or none
+    #[arg(long, value_name = "NAME")]
+    pub theme: Option<Theme>,
+
+    /// Stylesheet URL linked after the theme, can be repeated
+    #[arg(long = "stylesheet", value_name = "URL")]
+    pub stylesheets: Vec<String>,
+
+    /// Layout of the generated HTML: compact, pretty or minified
+    #[arg(long, value_name = "FORMAT")]
+    pub html_format: Option<HtmlFormat>,
+
+    /// Options from the `[render]` table of devy.toml
+    #[arg(skip)]
+    pub project: OptionsLayer,
+}
+
+impl FilterToggles {
+    /// The options articles start from, before their front matter is applied.
+    pub fn options(&self) -> Options {
+        Options::default().with(&self.project)
+    }
+
+    /// The options given on the command line, which take precedence over the front matter.
+    pub fn overrides(&self) -> OptionsLayer {
+        OptionsLayer {
+            mermaid_rate: self.mermaid_rate,
+            mermaid_cdn: self.mermaid_cdn.clone(),
+            line_numbers: self.line_numbers,
+            copy: self.copy,
+            theme: self.theme,
+            stylesheets: (!self.stylesheets.is_empty()).then(|| self.stylesheets.clone()),
+            html_format: self.html_format,
+            ..OptionsLayer::default()
+        }
+    }
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct BuildArgs {
+    /// Markdown files to render, concatenated in the given order
+    #[arg(required = true)]
+    pub inputs: Vec<PathBuf>,
+
+    /// Output file, `-` writes to stdout [default: first input with the format's extension]
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Directory outputs go to when no output file is given, from `build.output-dir` in devy.toml
+    #[arg(skip)]
+    pub output_dir: Option<PathBuf>,
+
+    /// Pandoc output format
+    #[arg(short = 't', long = "to", default_value = "html")]
+    pub format: String,
+
+    #[command(flatten)]
+    pub page: PageArgs,
+
+    #[command(flatten)]
+    pub filters: FilterToggles,
+}
+
+#[derive(Args, Debug, Clone, Default)]
+pub struct PageArgs {
+    /// HTML template articles are placed into [default: `build.template` in devy.toml, or the built-in one]
+    #[arg(long, value_name = "PATH")]
+    pub template: Option<PathBuf>,
+
+    /// Write the article as an HTML fragment instead of a full document
+    #[arg(long, conflicts_with = "template")]
+    pub no_template: bool,
+
+    /// Move inline scripts into a .js file next to the article and print the matching Content-Security-Policy
+    #[arg(long, conflicts_with = "no_template")]
+    pub csp: bool,
+
+    /// Leave the `<meta charset="UTF-8">` tag out of the page template's head
+    #[arg(long, conflicts_with = "no_template")]
+    pub no_utf8_meta: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct FilterArgs {
+    /// Target format, passed by pandoc as the first argument
+    pub format: Option<String>,
+
+    #[command(flatten)]
+    pub filters: FilterToggles,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct WatchArgs {
+    #[command(flatten)]
+    pub build: BuildArgs,
+
+    /// Other files that trigger a rebuild, on top of the inputs and local scripts they reference
+    #[arg(long, value_name = "PATH")]
+    pub include: Vec<PathBuf>,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ServeArgs {
+    #[command(flatten)]
+    pub watch: WatchArgs,
+
+    /// Address to listen on
+    #[arg(long, default_value = "127.0.0.1")]
+    pub host: String,
+
+    /// Port to listen on
+    #[arg(short, long, default_value_t = 8000)]
+    pub port: u16,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct SiteArgs {
+    /// Directory containing the markdown articles and their assets [default: `site.content-dir` in devy.toml]
+    pub content: Option<PathBuf>,
+
+    /// Directory the site is written to [default: `site.output-dir` in devy.toml, or public]
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Rebuild and copy every file, even if the output is newer than its source
+    #[arg(long)]
+    pub force: bool,
+
+    /// Configuration file the site is built with, changes to it rebuilding every article
+    #[arg(skip)]
+    pub config: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub page: PageArgs,
+
+    #[command(flatten)]
+    pub filters: FilterToggles,
+}
+
+impl BuildArgs {
+    /// The path the article is written to, `None` meaning stdout.
+    pub fn output_path(&self) -> Option<PathBuf> {
+        match &self.output {
+            Some(path) if path.as_os_str() == "-" => None,
+            Some(path) => Some(path.clone()),
+            None => {
+                let path = self.inputs[0].with_extension(self.extension());
+                match (&self.output_dir, path.file_name()) {
+                    (Some(dir), Some(name)) => Some(dir.join(name)),
+                    _ => Some(path),
+                }
+            }
+        }
+    }
+
+    /// Whether the article is rendered to HTML, and so placed into a page template.
+    pub fn is_html(&self) -> bool {
+        self.extension() == "html"
+    }
+
+    fn extension(&self) -> &str {
+        match self.format.split(['+', '-']).next().unwrap_or_default() {
+            "html" | "html4" | "html5" => "html",
+            "markdown" | "commonmark" | "commonmark_x" | "gfm" => "md",
+            "latex" => "tex",
+            "json" => "json",
+            "plain" => "txt",
+            other => other,
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use devy::OptionsLayer;
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+
+use crate::cli::{BuildArgs, Command, FilterToggles, PageArgs};
+
+const CONFIG_FILE: &str = "devy.toml";
+
+/// Project configuration read from `devy.toml`:
+///
+/// ```toml
+/// [render]
+/// mermaid-cdn = "/vendor/mermaid.min.js"
+/// mermaid-rate = 1000
+/// line-numbers = true
+/// copy = true
+/// theme = "auto"
+/// stylesheets = ["/css/site.css"]
+/// html-format = "pretty"
+///
+/// [build]
+/// output-dir = "out"
+/// template = "templates/article.html"
+/// csp = true
+///
+/// [site]
+/// content-dir = "content"
+/// output-dir = "public"
+/// ```
+///
+/// Command-line flags take precedence over the configuration, and so does the front matter for `[render]`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Config {
+    #[serde(default)]
+    pub render: OptionsLayer,
+    #[serde(default)]
+    pub build: BuildConfig,
+    #[serde(default)]
+    pub site: SiteConfig,
+    #[serde(flatten)]
+    unknown: BTreeMap<String, IgnoredAny>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct BuildConfig {
+    pub output_dir: Option<PathBuf>,
+    pub template: Option<PathBuf>,
+    pub csp: Option<bool>,
+    #[serde(flatten)]
+    unknown: BTreeMap<String, IgnoredAny>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct SiteConfig {
+    pub content_dir: Option<PathBuf>,
+    pub output_dir: Option<PathBuf>,
+    #[serde(flatten)]
+    unknown: BTreeMap<String, IgnoredAny>,
+}
+
+impl Config {
+    /// Reads the configuration at `path`, or the first `devy.toml` found in the current directory or its parents.
+    /// Without a configuration file every setting keeps its default.
+    ///
+    /// Relative paths in the file are resolved against the directory containing it.
+    pub fn load(
+        path: Option<&Path>,
+    ) -> Result<(Config, Option<PathBuf>), Box<dyn std::error::Error>> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => match find_config()? {
+                Some(path) => path,
+                None => return Ok((Config::default(), None)),
+            },
+        };
+
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| format!("could not read {}: {}", path.display(), e))?;
+        let mut config: Config =
+            toml::from_str(&contents).map_err(|e| format!("invalid {}: {}", path.display(), e))?;
+
+        let root = path.parent().unwrap_or(Path::new(""));
+        for dir in [
+            &mut config.build.output_dir,
+            &mut config.build.template,
+            &mut config.site.content_dir,
+            &mut config.site.output_dir,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *dir = root.join(&*dir);
+        }
+
+        Ok((config, Some(path)))
+    }
+
+    /// Dotted paths of the keys devy doesn't know about, such as `render.colour`.
+    pub fn unknown_keys(&self) -> Vec<String> {
+        let tables = [
+            ("", &self.unknown),
+            ("render.", &self.render.unknown),
+            ("build.", &self.build.unknown),
+            ("site.", &self.site.unknown),
+        ];
+        tables
+            .into_iter()
+            .flat_map(|(prefix, unknown)| {
+                unknown.keys().map(move |key| format!("{}{}", prefix, key))
+            })
+            .collect()
+    }
+
+    /// Fills in every setting of `command` that wasn't given on the command line.
+    pub fn apply(&self, command: &mut Command) {
+        match command {
+            Command::Build(args) => self.apply_build(args),
+            Command::Filter(args) => self.apply_filters(&mut args.filters),
+            Command::Watch(args) => self.apply_build(&mut args.build),
+            Command::Serve(args) => self.apply_build(&mut args.watch.build),
+            Command::Site(args) => {
+                self.apply_filters(&mut args.filters);
+                self.apply_page(&mut args.page);
+                if args.content.is_none() {
+                    args.content = self.site.content_dir.clone();
+                }
+                if args.output.is_none() {
+                    args.output = self.site.output_dir.clone();
+                }
+            }
+        }
+    }
+
+    fn apply_build(&self, args: &mut BuildArgs) {
+        self.apply_filters(&mut args.filters);
+        self.apply_page(&mut args.page);
+        args.output_dir = self.build.output_dir.clone();
+    }
+
+    fn apply_page(&self, page: &mut PageArgs) {
+        if page.template.is_none() && !page.no_template {
+            page.template = self.build.template.clone();
+        }
+        if !page.no_template {
+            page.csp |= self.build.csp.unwrap_or(false);
+        }
+    }
+
+    fn apply_filters(&self, filters: &mut FilterToggles) {
+        filters.project = self.render.clone();
+    }
+}
+
+fn find_config() -> std::io::Result<Option<PathBuf>> {
+    let cwd = std::env::current_dir()?;
+    Ok(cwd
+        .ancestors()
+        .map(|dir| dir.join(CONFIG_FILE))
+        .find(|path| path.is_file()))
+}
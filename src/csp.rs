@@ -0,0 +1,103 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use sha2::{Digest, Sha256};
+
+use crate::dom::{el, DomElement, HtmlFormat};
+use crate::template::Page;
+
+/// Moves the inline scripts of `page` into a single external script loaded from `src`, in place of the last of
+/// them, and returns its content. Returns `None` when the page has no inline script.
+///
+/// The scripts keep their order, and run after every external script that came before any of them.
+pub fn externalize_scripts(page: &mut Page, src: &str) -> Option<String> {
+    let mut content = String::new();
+    let mut last = None;
+    let mut scripts = vec![];
+    for script in page.scripts.0.drain(..) {
+        if is_inline_script(&script) {
+            content.push_str(script.inner_html(HtmlFormat::Compact).trim_end());
+            content.push_str(";\n");
+            last = Some(scripts.len());
+        } else {
+            scripts.push(script);
+        }
+    }
+
+    let last = last?;
+    scripts.insert(
+        last,
+        el("script")
+            .attr("src", src)
+            .attr("integrity", hash(&content)),
+    );
+    page.scripts.0 = scripts;
+    Some(content)
+}
+
+/// The value of a `Content-Security-Policy` header allowing the scripts and styles of `page`, rendered in `format`,
+/// and nothing else: local files, the origins of external scripts and stylesheets, and inline ones by hash.
+///
+/// Scripts and styles inside the article body are not covered, and inline event handlers are always blocked.
+pub fn content_security_policy(page: &Page, format: HtmlFormat) -> String {
+    let mut script_src = vec!["'self'".to_string()];
+    for script in &page.scripts.0 {
+        let source = match attribute(script, "src") {
+            Some(src) => origin(src),
+            None => Some(format!("'{}'", hash(&script.inner_html(format)))),
+        };
+        push_unique(&mut script_src, source);
+    }
+
+    let mut style_src = vec!["'self'".to_string()];
+    for style in &page.styles.0 {
+        let source = match attribute(style, "href") {
+            Some(href) => origin(href),
+            None => Some(format!("'{}'", hash(&style.inner_html(format)))),
+        };
+        push_unique(&mut style_src, source);
+    }
+
+    format!(
+        "script-src {}; style-src {}; object-src 'none'; base-uri 'self'",
+        script_src.join(" "),
+        style_src.join(" ")
+    )
+}
+
+/// The `sha256-` hash of `content`, as used by CSP sources and subresource integrity.
+fn hash(content: &str) -> String {
+    format!("sha256-{}", STANDARD.encode(Sha256::digest(content)))
+}
+
+fn is_inline_script(element: &DomElement) -> bool {
+    matches!(element, DomElement::Element { tag, .. } if tag == "script")
+        && attribute(element, "src").is_none()
+}
+
+fn attribute<'a>(element: &'a DomElement, key: &str) -> Option<&'a str> {
+    match element {
+        DomElement::Element { attributes, .. } => attributes
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str()),
+        DomElement::Text(_) | DomElement::RawHtml(_) => None,
+    }
+}
+
+/// The scheme and host of an absolute URL, `None` for URLs on the same origin.
+fn origin(url: &str) -> Option<String> {
+    let (scheme, rest) = match url.split_once("://") {
+        Some((scheme, rest)) => (format!("{}://", scheme), rest),
+        None => (String::new(), url.strip_prefix("//")?),
+    };
+    let host = rest.split(['/', '?', '#']).next()?;
+    (!host.is_empty()).then(|| format!("{}{}", scheme, host))
+}
+
+fn push_unique(sources: &mut Vec<String>, source: Option<String>) {
+    if let Some(source) = source {
+        if !sources.contains(&source) {
+            sources.push(source);
+        }
+    }
+}
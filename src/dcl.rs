@@ -1,7 +1,12 @@
+use std::collections::HashMap;
+
+use pest::iterators::Pair;
 use pest::Parser;
 use pest_derive::Parser;
 
-use crate::dom::{Dom, DomElement};
+use crate::dom::{el, Dom, DomElement};
+use crate::error::{DclError, Span};
+use crate::js::{replace_free_identifiers, string_literal};
 
 #[derive(Parser)]
 #[grammar = "dcl.pest"]
@@ -17,14 +22,36 @@ pub struct Statement {
     pub variable: String,
     pub component_kind: ComponentKind,
     pub value: Value,
+    pub span: Span,
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub enum ComponentKind {
     TextInput,
     TextArea,
     Paragraph,
     Radio,
+    /// A boolean, `true` or `false` as a constant.
+    Checkbox,
+    /// A number between `min` and `max`, picked with a slider, and shown next to it if `label` is set.
+    Range {
+        min: f64,
+        max: f64,
+        step: f64,
+        label: bool,
+    },
+    /// A button running its function when clicked, with the number of clicks as its value.
+    Button {
+        label: Option<String>,
+    },
+}
+
+/// A variable defined by a DCL block, with the variables its value is derived from, for the other blocks of the
+/// article to check their variables against.
+#[derive(Clone, Debug)]
+pub struct Definition {
+    pub variable: String,
+    pub dependencies: Vec<String>,
 }
 
 #[derive(Debug)]
@@ -41,148 +68,461 @@ pub enum Value {
     },
 }
 
-fn swap_variables_in_js(s: &str, vars: &Vec<String>) -> String {
-    let change = "document.getElementById(\"{}\").value";
-    let mut s = s.to_string();
+/// The runtime keeping the values of DCL variables, defining the `devy` store the scripts of DCL blocks use.
+pub const RUNTIME: &str = include_str!("runtime.js");
+
+/// Replaces the variables of a function body with their values in the store.
+fn swap_variables_in_js(s: &str, vars: &[String], span: Span) -> Result<String, DclError> {
+    replace_free_identifiers(s, |name| {
+        vars.iter()
+            .any(|var| var == name)
+            .then(|| format!("devy.get(\"{}\")", name))
+    })
+    .map_err(|message| DclError::new(format!("invalid function body: {}", message), span))
+}
 
-    for var in vars {
-        if change.contains(var) {
-            panic!("Variable {} is already in the change string, that would cause ambiguity", var);
+impl DeclarativeComponentLanguage {
+    /// The components followed by a script wiring them together, as a standalone piece of HTML.
+    pub fn to_dom(&self) -> Result<Dom, DclError> {
+        let (mut dom, script) = self.render()?;
+        if !script.is_empty() {
+            dom.0.push(DomElement::deferred_script(&format!(
+                "{}\n{}",
+                RUNTIME, script
+            )));
         }
-        s = s.replace(var, &format!("document.getElementById(\"{}\").value", var));
+        Ok(dom)
     }
 
-    s
-}
+    /// The markup of the components, and the Javascript wiring them together, to be run once the page is loaded
+    /// after [`RUNTIME`].
+    pub fn render(&self) -> Result<(Dom, String), DclError> {
+        self.render_in(&[])
+    }
 
-impl DeclarativeComponentLanguage {
-    pub fn to_dom(&self) -> Dom {
+    /// The variables the block defines, and the variables each one is derived from.
+    pub fn definitions(&self) -> Vec<Definition> {
+        self.statements
+            .iter()
+            .map(|statement| Definition {
+                variable: statement.variable.clone(),
+                dependencies: statement.dependencies().to_vec(),
+            })
+            .collect()
+    }
+
+    /// Like [`render`](DeclarativeComponentLanguage::render), for a block of an article whose other DCL blocks
+    /// define `article`. Functions may depend on their variables too, as long as no variable ends up depending on
+    /// itself through them.
+    pub fn render_in(&self, article: &[Definition]) -> Result<(Dom, String), DclError> {
+        let graph = DependencyGraph::new(&self.statements, article)?;
         let mut dom = vec![];
+        let mut script = String::new();
 
-        for statement in &self.statements {
-            match &statement.value {
-                Value::Const { value } => dom.push(DomElement::Element {
-                    tag: statement.component_kind.tag(),
-                    attributes: [
-                        statement.component_kind.attributes(),
-                        vec![
-                            ("id".to_string(), statement.variable.clone()),
-                            ("value".to_string(), value.clone()),
-                        ],
-                    ]
-                    .concat(),
-                    children: Dom(vec![]),
-                }),
+        // Declare the variables in the store, each one after the variables it depends on
+        for &index in &graph.order {
+            let statement = &self.statements[index];
+            let declaration = match &statement.value {
+                // Buttons count their clicks, their function only runs when they are clicked
+                _ if matches!(statement.component_kind, ComponentKind::Button { .. }) => {
+                    format!("devy.declare(\"{}\", 0);\n", statement.variable)
+                }
+                Value::Const { value } => format!(
+                    "devy.declare(\"{}\", {});\n",
+                    statement.variable,
+                    statement.component_kind.literal(value)
+                ),
                 Value::Fn { variables, body } => {
-                    // Create event listeners for each variable, and update the value of the input
-                    let body_with_query_selectors = swap_variables_in_js(body, variables);
-
-                    for variable in variables {
-                        let event_listener = format!(
-                            r#"
-    document.getElementById("{}").addEventListener('input', function(event) {{
-    document.getElementById("{}").{} = {}
-}});
-"#,
-                            variable,
-                            statement.variable,
-                            statement.component_kind.accessor(),
-                            body_with_query_selectors
-                        );
-                        dom.push(DomElement::script(&event_listener));
+                    let dependencies: Vec<String> =
+                        variables.iter().map(|v| string_literal(v)).collect();
+                    format!(
+                        "devy.derive(\"{}\", [{}], function () {{ return {}; }});\n",
+                        statement.variable,
+                        dependencies.join(", "),
+                        swap_variables_in_js(body, variables, statement.span)?
+                    )
+                }
+                // Nothing is selected until the reader picks an option
+                Value::Options { .. } => {
+                    format!("devy.declare(\"{}\", null);\n", statement.variable)
+                }
+            };
+            script.push_str(&declaration);
+        }
+
+        for statement in &self.statements {
+            let variable = &statement.variable;
+            if let (ComponentKind::Button { label }, Value::Fn { variables, body }) =
+                (&statement.component_kind, &statement.value)
+            {
+                // Apply the changes returned by the function when the button is clicked
+                dom.push(
+                    statement
+                        .component_kind
+                        .element()
+                        .id(variable)
+                        .text(label.as_deref().unwrap_or(variable)),
+                );
+                script.push_str(&format!(
+                    "document.getElementById(\"{}\").addEventListener('click', function (event) {{ devy.click(\"{}\", function () {{ return {}; }}); }});\n",
+                    variable,
+                    variable,
+                    swap_variables_in_js(body, variables, statement.span)?
+                ));
+                continue;
+            }
+            let Value::Options { values } = &statement.value else {
+                let component = statement.component_kind.element().id(variable);
+                dom.push(match &statement.value {
+                    // Paragraphs and text areas have no value attribute, their initial value is their content
+                    Value::Const { value }
+                        if matches!(
+                            statement.component_kind,
+                            ComponentKind::Paragraph | ComponentKind::TextArea
+                        ) =>
+                    {
+                        component.text(value)
+                    }
+                    Value::Const { value }
+                        if statement.component_kind == ComponentKind::Checkbox =>
+                    {
+                        component.flag("checked", value == "true")
                     }
+                    Value::Const { value } => component.attr("value", value),
+                    _ => component,
+                });
 
-                    dom.push(DomElement::Element {
-                        tag: statement.component_kind.tag(),
-                        attributes: [
-                            statement.component_kind.attributes(),
-                            vec![("id".to_string(), statement.variable.clone())],
-                        ]
-                        .concat(),
-                        children: Dom(vec![]),
-                    });
+                // Render the component whenever its value changes, and store the values the reader enters. Constant
+                // paragraphs are written as text like in the markup, only functions can produce HTML.
+                let accessor = match (&statement.component_kind, &statement.value) {
+                    (ComponentKind::Paragraph, Value::Const { .. }) => "textContent".to_string(),
+                    (kind, _) => kind.accessor(),
+                };
+                script.push_str(&format!(
+                    "devy.subscribe(\"{}\", function (value) {{ document.getElementById(\"{}\").{} = {}; }});\n",
+                    variable,
+                    variable,
+                    accessor,
+                    statement.component_kind.displayed("value"),
+                ));
+                if statement.component_kind != ComponentKind::Paragraph {
+                    script.push_str(&format!(
+                        "document.getElementById(\"{}\").addEventListener('input', function (event) {{ devy.set(\"{}\", {}); }});\n",
+                        variable,
+                        variable,
+                        statement.component_kind.entered("event.target"),
+                    ));
                 }
-                Value::Options { values } => {
-                    // Create a hidden input variable to store the selected value
-                    dom.push(DomElement::Element {
-                        tag: "input".to_string(),
-                        attributes: vec![
-                            ("type".to_string(), "hidden".to_string()),
-                            ("id".to_string(), statement.variable.to_string()),
-                        ],
-                        children: Dom(vec![]),
-                    });
-
-                    // Create radio buttons for each value
-                    for value in values {
-                        let event_listener = format!(
-                            r#"
-    document.getElementById("{}").addEventListener('input', function(event) {{
-    document.getElementById("{}").value = "{}";
-    document.getElementById("{}").dispatchEvent(new Event('input'));
-}});
-"#,
-                            format_args!("{}_{}", statement.variable, value),
-                            statement.variable,
-                            value,
-                            statement.variable,
-                        );
-                        dom.push(DomElement::Element {
-                            tag: "input".to_string(),
-                            attributes: vec![
-                                ("type".to_string(), "radio".to_string()),
-                                ("name".to_string(), statement.variable.clone()),
-                                ("value".to_string(), value.clone()),
-                                (
-                                    "id".to_string(),
-                                    format!("{}_{}", statement.variable, value),
-                                ),
-                            ],
-                            children: Dom(vec![]),
-                        });
-
-                        // Register event listener
-                        dom.push(DomElement::Element {
-                            tag: "script".to_string(),
-                            attributes: vec![],
-                            children: Dom(vec![DomElement::Text(event_listener)]),
-                        });
-
-                        // Create a label for the radio button
-
-                        dom.push(DomElement::Element {
-                            tag: "label".to_string(),
-                            attributes: vec![(
-                                "for".to_string(),
-                                format!("{}_{}", statement.variable, value),
-                            )],
-                            children: Dom(vec![DomElement::Text(value.clone())]),
-                        });
-                    }
 
-                    // Create a hidden input variable to store the selected value
+                if let ComponentKind::Range { label: true, .. } = statement.component_kind {
+                    let id = format!("{}-label", variable);
+                    let initial = match &statement.value {
+                        Value::Const { value } => value.as_str(),
+                        _ => "",
+                    };
+                    dom.push(
+                        el("output")
+                            .class("devy-range-label")
+                            .attr("for", variable)
+                            .id(&id)
+                            .text(initial),
+                    );
+                    script.push_str(&format!(
+                        "devy.subscribe(\"{}\", function (value) {{ document.getElementById(\"{}\").textContent = value ?? \"\"; }});\n",
+                        variable, id
+                    ));
                 }
+                continue;
+            };
+
+            // Create radio buttons for each value, checking the one holding the value of the variable
+            script.push_str(&format!(
+                "devy.subscribe(\"{}\", function (value) {{ document.getElementsByName(\"{}\").forEach(function (radio) {{ radio.checked = radio.value === value; }}); }});\n",
+                variable, variable
+            ));
+            for value in values {
+                let id = format!("{}_{}", variable, value);
+                dom.push(
+                    statement
+                        .component_kind
+                        .element()
+                        .attr("name", variable)
+                        .attr("value", value)
+                        .id(&id),
+                );
+                script.push_str(&format!(
+                    "document.getElementById(\"{}\").addEventListener('input', function (event) {{ devy.set(\"{}\", {}); }});\n",
+                    id,
+                    variable,
+                    string_literal(value)
+                ));
+
+                // Create a label for the radio button
+                dom.push(
+                    el("label")
+                        .class("devy-radio-label")
+                        .attr("for", &id)
+                        .text(value),
+                );
+            }
+        }
+
+        Ok((Dom(dom), script))
+    }
+}
+
+/// The dependencies between the statements of a DCL block, from the variables of function values.
+struct DependencyGraph {
+    /// Every statement, each one after the statements it depends on.
+    order: Vec<usize>,
+}
+
+impl DependencyGraph {
+    /// Builds the graph of `statements`, in an article whose other DCL blocks define `article`. Rejects variables
+    /// defined twice, in the block or by another one, variables defined by no block, and cycles, including those
+    /// going through the variables of other blocks.
+    fn new(statements: &[Statement], article: &[Definition]) -> Result<Self, DclError> {
+        // The statements come first, followed by the definitions of the other blocks
+        let mut indices = HashMap::new();
+        for (index, statement) in statements.iter().enumerate() {
+            if indices.insert(statement.variable.as_str(), index).is_some() {
+                return Err(DclError::new(
+                    format!("`{}` is defined more than once", statement.variable),
+                    statement.span,
+                ));
+            }
+        }
+        for statement in statements {
+            if article.iter().any(|d| d.variable == statement.variable) {
+                return Err(DclError::new(
+                    format!(
+                        "`{}` is also defined by another DCL block",
+                        statement.variable
+                    ),
+                    statement.span,
+                ));
+            }
+        }
+        for (index, definition) in article.iter().enumerate() {
+            indices
+                .entry(definition.variable.as_str())
+                .or_insert(statements.len() + index);
+        }
+        let name = |index: usize| match statements.get(index) {
+            Some(statement) => statement.variable.as_str(),
+            None => article[index - statements.len()].variable.as_str(),
+        };
+
+        let mut dependencies = vec![vec![]; statements.len() + article.len()];
+        for (index, statement) in statements.iter().enumerate() {
+            let Value::Fn { variables, .. } = &statement.value else {
+                continue;
             };
+            for variable in variables {
+                if !indices.contains_key(variable.as_str()) {
+                    return Err(DclError::new(
+                        format!(
+                            "`{}` depends on `{}`, which is not defined by any DCL block",
+                            statement.variable, variable
+                        ),
+                        statement.span,
+                    ));
+                }
+            }
+            dependencies[index] = edges(statement.dependencies(), &indices);
+        }
+        for (index, definition) in article.iter().enumerate() {
+            // Variables no block defines are reported by the block using them
+            dependencies[statements.len() + index] = edges(&definition.dependencies, &indices);
         }
 
-        Dom(dom)
+        for (index, statement) in statements.iter().enumerate() {
+            if let Some(cycle) = cycle(index, &dependencies) {
+                let names: Vec<&str> = cycle.iter().map(|&i| name(i)).collect();
+                return Err(DclError::new(
+                    format!(
+                        "`{}` depends on itself: {}",
+                        statement.variable,
+                        names.join(" -> ")
+                    ),
+                    statement.span,
+                ));
+            }
+        }
+
+        // Depth-first search from every statement, placing each one after the statements it depends on
+        let mut order = vec![];
+        let mut visited = vec![false; statements.len()];
+        for index in 0..statements.len() {
+            visit(index, &dependencies, &mut visited, &mut order);
+        }
+
+        Ok(DependencyGraph { order })
+    }
+}
+
+/// The indices of the defined `variables`, without duplicates.
+fn edges(variables: &[String], indices: &HashMap<&str, usize>) -> Vec<usize> {
+    let mut edges = vec![];
+    for variable in variables {
+        if let Some(&index) = indices.get(variable.as_str()) {
+            if !edges.contains(&index) {
+                edges.push(index);
+            }
+        }
+    }
+    edges
+}
+
+/// A path of dependencies from `start` back to itself, if `start` depends on itself.
+fn cycle(start: usize, dependencies: &[Vec<usize>]) -> Option<Vec<usize>> {
+    let mut path = vec![start];
+    let mut visited = vec![false; dependencies.len()];
+    path_to(start, dependencies, &mut visited, &mut path).then_some(path)
+}
+
+/// Extends `path` with dependencies until it reaches `target`, returning whether it did.
+fn path_to(
+    target: usize,
+    dependencies: &[Vec<usize>],
+    visited: &mut [bool],
+    path: &mut Vec<usize>,
+) -> bool {
+    let last = *path.last().unwrap();
+    for &dependency in &dependencies[last] {
+        if dependency == target {
+            path.push(target);
+            return true;
+        }
+        if !visited[dependency] {
+            visited[dependency] = true;
+            path.push(dependency);
+            if path_to(target, dependencies, visited, path) {
+                return true;
+            }
+            path.pop();
+        }
+    }
+    false
+}
+
+/// Visits the statement `index` and the statements it depends on, pushing each one to `order` once its dependencies
+/// are. Dependencies on other blocks, past the end of `visited`, are left to the runtime.
+fn visit(index: usize, dependencies: &[Vec<usize>], visited: &mut [bool], order: &mut Vec<usize>) {
+    if visited[index] {
+        return;
+    }
+    visited[index] = true;
+    for &dependency in &dependencies[index] {
+        if dependency < visited.len() {
+            visit(dependency, dependencies, visited, order);
+        }
+    }
+    order.push(index);
+}
+
+impl Statement {
+    /// The variables the value of the statement is derived from. Buttons read their variables when clicked, which
+    /// is not a dependency of their value.
+    fn dependencies(&self) -> &[String] {
+        match (&self.component_kind, &self.value) {
+            (ComponentKind::Button { .. }, _) => &[],
+            (_, Value::Fn { variables, .. }) => variables,
+            _ => &[],
+        }
     }
 }
 
 impl ComponentKind {
-    pub fn attributes(&self) -> Vec<(String, String)> {
+    /// An empty element of the component, with its type and class.
+    pub fn element(&self) -> DomElement {
+        let element = el(&self.tag()).class(&self.class());
+        match self {
+            ComponentKind::TextInput => element.attr("type", "text"),
+            ComponentKind::Radio => element.attr("type", "radio"),
+            ComponentKind::Checkbox => element.attr("type", "checkbox"),
+            ComponentKind::Button { .. } => element.attr("type", "button"),
+            ComponentKind::Range { min, max, step, .. } => element
+                .attr("type", "range")
+                .attr("min", min.to_string())
+                .attr("max", max.to_string())
+                .attr("step", step.to_string()),
+            ComponentKind::TextArea | ComponentKind::Paragraph => element,
+        }
+    }
+
+    /// The CSS class themes style the component with.
+    pub fn class(&self) -> String {
         match self {
-            ComponentKind::TextInput => vec![("type".to_string(), "text".to_string())],
-            ComponentKind::Radio => vec![("type".to_string(), "radio".to_string())],
-            ComponentKind::TextArea | ComponentKind::Paragraph => vec![],
+            ComponentKind::TextInput => "devy-text-input".to_string(),
+            ComponentKind::TextArea => "devy-text-area".to_string(),
+            ComponentKind::Paragraph => "devy-paragraph".to_string(),
+            ComponentKind::Radio => "devy-radio".to_string(),
+            ComponentKind::Checkbox => "devy-checkbox".to_string(),
+            ComponentKind::Range { .. } => "devy-range".to_string(),
+            ComponentKind::Button { .. } => "devy-button".to_string(),
         }
     }
 
     pub fn accessor(&self) -> String {
         match self {
-            ComponentKind::TextInput | ComponentKind::TextArea => "value".to_string(),
+            ComponentKind::TextInput | ComponentKind::TextArea | ComponentKind::Range { .. } => {
+                "value".to_string()
+            }
             ComponentKind::Paragraph => "innerHTML".to_string(),
-            ComponentKind::Radio => "checked".to_string(),
+            ComponentKind::Button { .. } => "textContent".to_string(),
+            ComponentKind::Radio | ComponentKind::Checkbox => "checked".to_string(),
+        }
+    }
+
+    /// The Javascript expression assigned to the [accessor](ComponentKind::accessor) to show the value `value`.
+    pub fn displayed(&self, value: &str) -> String {
+        match self {
+            ComponentKind::TextInput
+            | ComponentKind::TextArea
+            | ComponentKind::Paragraph
+            | ComponentKind::Range { .. }
+            | ComponentKind::Button { .. } => format!("{} ?? \"\"", value),
+            ComponentKind::Radio | ComponentKind::Checkbox => format!("Boolean({})", value),
+        }
+    }
+
+    /// The Javascript expression of the value the reader entered into `element`, typed like the component's values.
+    pub fn entered(&self, element: &str) -> String {
+        match self {
+            ComponentKind::Range { .. } => format!("Number({}.value)", element),
+            _ => format!("{}.{}", element, self.accessor()),
+        }
+    }
+
+    /// The Javascript literal of the constant value `value`, a boolean for checkboxes, a number for ranges and a
+    /// string otherwise.
+    pub fn literal(&self, value: &str) -> String {
+        match self {
+            ComponentKind::Checkbox | ComponentKind::Range { .. } => value.to_string(),
+            _ => string_literal(value),
+        }
+    }
+
+    /// Checks a constant value against the kind of the component, returning it as written in the component.
+    fn constant(&self, value: &str) -> Result<String, String> {
+        match self {
+            ComponentKind::Checkbox => match value.trim_end() {
+                value @ ("true" | "false") => Ok(value.to_string()),
+                value => Err(format!("a checkbox is `true` or `false`, not `{}`", value)),
+            },
+            ComponentKind::Button { .. } => {
+                Err("a button runs a function when clicked, like `n => ({ n: n + 1 })`".to_string())
+            }
+            ComponentKind::Range { min, max, .. } => {
+                let value = value.trim_end();
+                match value.parse::<f64>() {
+                    Ok(number) if (*min..=*max).contains(&number) => Ok(value.to_string()),
+                    Ok(_) => Err(format!("`{}` is not between {} and {}", value, min, max)),
+                    Err(_) => Err(format!("a range is a number, not `{}`", value)),
+                }
+            }
+            _ => Ok(value.to_string()),
         }
     }
 
@@ -191,32 +531,58 @@ impl ComponentKind {
             ComponentKind::TextInput => "input".to_string(),
             ComponentKind::TextArea => "textarea".to_string(),
             ComponentKind::Paragraph => "p".to_string(),
-            ComponentKind::Radio => "input".to_string(),
+            ComponentKind::Radio | ComponentKind::Checkbox | ComponentKind::Range { .. } => {
+                "input".to_string()
+            }
+            ComponentKind::Button { .. } => "button".to_string(),
         }
     }
 }
 
-pub fn parse_dcl(s: &str) -> DeclarativeComponentLanguage {
-    let pairs = DclParser::parse(Rule::document, s).unwrap_or_else(|e| panic!("{}", e));
+/// Parses the source of a DCL code block into its statements.
+pub fn parse_dcl(s: &str) -> Result<DeclarativeComponentLanguage, DclError> {
+    let pairs = DclParser::parse(Rule::document, s)?;
 
     let mut statements = vec![];
 
     for pair in pairs {
         let statement = match pair.as_rule() {
             Rule::stmt => {
+                let start = pair.as_span().start();
+                let span = Span::new(start, start + pair.as_str().trim_end().len());
                 let mut pairs = pair.into_inner();
                 let variable = pairs.next().unwrap().as_str().trim().to_string();
-                let component_kind = match pairs.next().unwrap().as_str() {
+                let kind = pairs.next().unwrap();
+                let component_kind = match kind.as_str() {
                     "text-input" => ComponentKind::TextInput,
                     "text-area" => ComponentKind::TextArea,
                     "paragraph" => ComponentKind::Paragraph,
                     "radio" => ComponentKind::Radio,
-                    _ => panic!(),
+                    "checkbox" => ComponentKind::Checkbox,
+                    other if other.starts_with("range") => {
+                        parse_range(kind.into_inner().next().unwrap())?
+                    }
+                    other if other.starts_with("button") => {
+                        let button = kind.into_inner().next().unwrap();
+                        let label = button
+                            .into_inner()
+                            .next()
+                            .map(|string| string.as_str().trim_matches('"').to_string());
+                        ComponentKind::Button { label }
+                    }
+                    other => {
+                        return Err(DclError::new(
+                            format!("unknown component type `{}`", other),
+                            kind.as_span().into(),
+                        ))
+                    }
                 };
                 let pair = pairs.next().unwrap();
                 let value = match pair.as_rule() {
                     Rule::constant => {
-                        let value = pair.as_str().to_string();
+                        let value = component_kind
+                            .constant(pair.as_str())
+                            .map_err(|message| DclError::new(message, pair.as_span().into()))?;
                         Value::Const { value }
                     }
                     Rule::function => {
@@ -226,33 +592,216 @@ pub fn parse_dcl(s: &str) -> DeclarativeComponentLanguage {
                             .as_str()
                             .split(',')
                             .map(|s| s.trim().to_string())
+                            .filter(|s| !s.is_empty())
                             .collect();
                         let body = pairs.next().unwrap().as_str().to_string();
                         Value::Fn { variables, body }
                     }
+                    Rule::options if component_kind == ComponentKind::Checkbox => {
+                        return Err(DclError::new(
+                            "a checkbox has no options, it is either checked or not",
+                            pair.as_span().into(),
+                        ))
+                    }
+                    Rule::options if matches!(component_kind, ComponentKind::Button { .. }) => {
+                        return Err(DclError::new(
+                            "a button has no options, it runs a function when clicked",
+                            pair.as_span().into(),
+                        ))
+                    }
+                    Rule::options if matches!(component_kind, ComponentKind::Range { .. }) => {
+                        return Err(DclError::new(
+                            "a range has no options, its bounds are written as `range(min, max, step)`",
+                            pair.as_span().into(),
+                        ))
+                    }
                     Rule::options => {
                         let pairs = pair.into_inner();
                         let values = pairs.map(|p| p.as_str().to_string()).collect();
                         Value::Options { values }
                     }
-                    other => panic!("{:?}", other),
+                    other => {
+                        return Err(DclError::new(
+                            format!("unexpected {:?} in statement", other),
+                            pair.as_span().into(),
+                        ))
+                    }
                 };
                 Statement {
                     variable,
                     component_kind,
                     value,
+                    span,
                 }
             }
             Rule::EOI => continue,
-            other => panic!("{:?}", other),
+            other => {
+                return Err(DclError::new(
+                    format!("unexpected {:?} at top level", other),
+                    pair.as_span().into(),
+                ))
+            }
         };
         statements.push(statement);
     }
 
-    DeclarativeComponentLanguage { statements }
+    Ok(DeclarativeComponentLanguage { statements })
 }
 
-pub fn interpret_dcl(s: &str) -> Dom {
-    let dcl = parse_dcl(s);
+/// Parses the `range(min, max, step) labeled` type of a range component, where the step and label are optional.
+fn parse_range(pair: Pair<Rule>) -> Result<ComponentKind, DclError> {
+    let span = pair.as_span().into();
+    let mut numbers = vec![];
+    let mut label = false;
+    for pair in pair.into_inner() {
+        match pair.as_rule() {
+            Rule::number => numbers.push(pair.as_str().parse::<f64>().map_err(|_| {
+                DclError::new(
+                    format!("`{}` is not a number", pair.as_str()),
+                    pair.as_span().into(),
+                )
+            })?),
+            _ => label = true,
+        }
+    }
+
+    let (min, max, step) = (
+        numbers[0],
+        numbers[1],
+        numbers.get(2).copied().unwrap_or(1.0),
+    );
+    if min >= max {
+        return Err(DclError::new(
+            format!(
+                "the minimum of a range must be below its maximum, {} is not below {}",
+                min, max
+            ),
+            span,
+        ));
+    }
+    if step <= 0.0 {
+        return Err(DclError::new(
+            format!("the step of a range must be positive, not {}", step),
+            span,
+        ));
+    }
+    Ok(ComponentKind::Range {
+        min,
+        max,
+        step,
+        label,
+    })
+}
+
+/// Parses a DCL code block and renders its components.
+pub fn interpret_dcl(s: &str) -> Result<Dom, DclError> {
+    let dcl = parse_dcl(s)?;
     dcl.to_dom()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The variables of `source` in the order they are declared, or the message of the error rejecting them.
+    fn order(source: &str, article: &[Definition]) -> Result<Vec<String>, String> {
+        let dcl = parse_dcl(source).unwrap();
+        let graph = DependencyGraph::new(&dcl.statements, article).map_err(|e| e.message)?;
+        Ok(graph
+            .order
+            .iter()
+            .map(|&i| dcl.statements[i].variable.clone())
+            .collect())
+    }
+
+    fn definition(variable: &str, dependencies: &[&str]) -> Definition {
+        Definition {
+            variable: variable.to_string(),
+            dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn orders_variables_after_their_dependencies() {
+        assert_eq!(
+            order(
+                "c : paragraph := b => b\nb : paragraph := a => a\na : text-input := x\n",
+                &[]
+            ),
+            Ok(vec!["a".to_string(), "b".to_string(), "c".to_string()])
+        );
+    }
+
+    #[test]
+    fn rejects_cycles() {
+        assert_eq!(
+            order("a : paragraph := a => a\n", &[]),
+            Err("`a` depends on itself: a -> a".to_string())
+        );
+        assert_eq!(
+            order(
+                "a : text-input := x\nb : paragraph := c => c\nc : paragraph := b => b\n",
+                &[]
+            ),
+            Err("`b` depends on itself: b -> c -> b".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_cycles_through_other_blocks() {
+        assert_eq!(
+            order("p : paragraph := q => q\n", &[definition("q", &["p"])]),
+            Err("`p` depends on itself: p -> q -> p".to_string())
+        );
+        assert_eq!(
+            order(
+                "p : paragraph := q => q\n",
+                &[definition("q", &["r"]), definition("r", &["p"])]
+            ),
+            Err("`p` depends on itself: p -> q -> r -> p".to_string())
+        );
+        // A cycle between other blocks is reported by those blocks
+        assert_eq!(
+            order(
+                "p : paragraph := q => q\n",
+                &[definition("q", &["r"]), definition("r", &["q"])]
+            ),
+            Ok(vec!["p".to_string()])
+        );
+    }
+
+    #[test]
+    fn leaves_buttons_out_of_cycles() {
+        assert_eq!(
+            order(
+                "next : button := count => ({ count: count + 1 })\ncount : paragraph := next => next\n",
+                &[]
+            ),
+            Ok(vec!["next".to_string(), "count".to_string()])
+        );
+    }
+
+    #[test]
+    fn rejects_variables_defined_twice() {
+        assert_eq!(
+            order("a : text-input := x\na : text-input := y\n", &[]),
+            Err("`a` is defined more than once".to_string())
+        );
+        assert_eq!(
+            order("a : text-input := x\n", &[definition("a", &[])]),
+            Err("`a` is also defined by another DCL block".to_string())
+        );
+    }
+
+    #[test]
+    fn resolves_variables_against_every_block() {
+        assert_eq!(
+            order("b : paragraph := a => a\n", &[definition("a", &[])]),
+            Ok(vec!["b".to_string()])
+        );
+        assert_eq!(
+            order("b : paragraph := a => a\n", &[]),
+            Err("`b` depends on `a`, which is not defined by any DCL block".to_string())
+        );
+    }
+}
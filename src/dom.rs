@@ -1,34 +1,277 @@
-#[derive(Clone, Debug)]
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+#[derive(Clone, Debug, Default)]
 pub struct Dom(pub Vec<DomElement>);
 
+/// Elements whose text is not HTML, such as script sources, and so can only be protected from closing the element.
+const RAW_TEXT_ELEMENTS: [&str; 2] = ["script", "style"];
+
+/// Elements that cannot have children, written without a closing tag.
+const VOID_ELEMENTS: [&str; 13] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track",
+    "wbr",
+];
+
+/// Attributes that are set by being present. They are written without a value, or left out when their value is
+/// `false`.
+const BOOLEAN_ATTRIBUTES: [&str; 11] = [
+    "async",
+    "autofocus",
+    "checked",
+    "defer",
+    "disabled",
+    "hidden",
+    "multiple",
+    "open",
+    "readonly",
+    "required",
+    "selected",
+];
+
+/// Elements whose whitespace is part of their content.
+const PREFORMATTED_ELEMENTS: [&str; 2] = ["pre", "textarea"];
+
+/// Elements laid out within a line of text, which pretty-printing keeps on the line of their parent.
+const INLINE_ELEMENTS: [&str; 20] = [
+    "a", "abbr", "b", "br", "button", "code", "em", "i", "img", "input", "kbd", "label", "mark",
+    "select", "small", "span", "strong", "sub", "sup", "textarea",
+];
+
+/// How [`Dom::to_html`] lays out the markup.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum HtmlFormat {
+    /// Elements written one after the other, with no whitespace added or removed.
+    #[default]
+    Compact,
+    /// Block elements on their own lines, indented by depth, for reading and diffing.
+    Pretty,
+    /// Whitespace in text collapsed and trimmed from scripts and styles, for production.
+    Minified,
+}
+
+impl HtmlFormat {
+    pub const NAMES: [&'static str; 3] = ["compact", "pretty", "minified"];
+}
+
+impl FromStr for HtmlFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "compact" => Ok(HtmlFormat::Compact),
+            "pretty" => Ok(HtmlFormat::Pretty),
+            "minified" => Ok(HtmlFormat::Minified),
+            other => Err(format!(
+                "unknown HTML format `{}`, expected one of {}",
+                other,
+                HtmlFormat::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+impl fmt::Display for HtmlFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            HtmlFormat::Compact => "compact",
+            HtmlFormat::Pretty => "pretty",
+            HtmlFormat::Minified => "minified",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Where text is written, which decides how it is escaped and whether its whitespace can change.
+#[derive(Clone, Copy)]
+enum TextContext<'a> {
+    Flow,
+    Preformatted,
+    /// The content of a `<script>` or `<style>` element, with its tag.
+    Raw(&'a str),
+}
+
 impl Dom {
+    /// The elements as compact HTML, see [`HtmlFormat::Compact`].
     pub fn to_raw_html(&self) -> String {
+        self.to_html(HtmlFormat::Compact)
+    }
+
+    pub fn to_html(&self, format: HtmlFormat) -> String {
         let mut html = String::new();
-        for element in &self.0 {
-            match element {
-                DomElement::Text(text) => html.push_str(text),
-                DomElement::Element {
-                    tag,
-                    attributes,
-                    children,
-                } => {
-                    html.push_str(&format!("<{}", tag));
-                    for (key, value) in attributes {
-                        html.push_str(&format!(" {}=\"{}\" ", key, value));
-                    }
-                    html.push('>');
-                    html.push_str(&children.to_raw_html());
-                    html.push_str(&format!("</{}>", tag));
-                }
+        if format == HtmlFormat::Pretty {
+            for element in &self.0 {
+                write_pretty(&mut html, element, 0);
             }
+        } else {
+            write_elements(&mut html, &self.0, TextContext::Flow, format);
         }
         html
     }
 }
 
+/// The context of the children of a `<tag>` element written in `parent`.
+fn child_context<'a>(tag: &'a str, parent: TextContext<'a>) -> TextContext<'a> {
+    if RAW_TEXT_ELEMENTS.contains(&tag) {
+        TextContext::Raw(tag)
+    } else if PREFORMATTED_ELEMENTS.contains(&tag) {
+        TextContext::Preformatted
+    } else {
+        parent
+    }
+}
+
+fn write_elements(
+    html: &mut String,
+    elements: &[DomElement],
+    context: TextContext,
+    format: HtmlFormat,
+) {
+    let minify = format == HtmlFormat::Minified;
+    for element in elements {
+        match element {
+            DomElement::Text(text) => match context {
+                TextContext::Raw(tag) if minify => {
+                    html.push_str(&escape_raw_text(text.trim(), tag))
+                }
+                TextContext::Raw(tag) => html.push_str(&escape_raw_text(text, tag)),
+                TextContext::Flow if minify => {
+                    html.push_str(&escape_text(&collapse_whitespace(text)))
+                }
+                TextContext::Flow | TextContext::Preformatted => html.push_str(&escape_text(text)),
+            },
+            DomElement::RawHtml(raw) => html.push_str(raw),
+            DomElement::Element {
+                tag,
+                attributes,
+                children,
+            } => {
+                write_open_tag(html, tag, attributes);
+                if VOID_ELEMENTS.contains(&tag.as_str()) {
+                    debug_assert!(children.0.is_empty(), "<{}> cannot have children", tag);
+                    continue;
+                }
+                write_elements(html, &children.0, child_context(tag, context), format);
+                html.push_str(&format!("</{}>", tag));
+            }
+        }
+    }
+}
+
+/// Writes `element` on its own lines, indented by `depth`. Only elements whose children are all block elements
+/// are broken up, as whitespace anywhere else could change how the page looks.
+fn write_pretty(html: &mut String, element: &DomElement, depth: usize) {
+    let indent = "  ".repeat(depth);
+    html.push_str(&indent);
+
+    if let DomElement::Element {
+        tag,
+        attributes,
+        children,
+    } = element
+    {
+        let is_block = |element: &DomElement| match element {
+            DomElement::Element { tag, .. } => !INLINE_ELEMENTS.contains(&tag.as_str()),
+            DomElement::Text(_) | DomElement::RawHtml(_) => false,
+        };
+        let breaks = is_block(element)
+            && ![
+                RAW_TEXT_ELEMENTS.as_slice(),
+                PREFORMATTED_ELEMENTS.as_slice(),
+            ]
+            .concat()
+            .contains(&tag.as_str())
+            && !children.0.is_empty()
+            && children.0.iter().all(is_block);
+        if breaks {
+            write_open_tag(html, tag, attributes);
+            html.push('\n');
+            for child in &children.0 {
+                write_pretty(html, child, depth + 1);
+            }
+            html.push_str(&format!("{}</{}>\n", indent, tag));
+            return;
+        }
+    }
+
+    write_elements(
+        html,
+        std::slice::from_ref(element),
+        TextContext::Flow,
+        HtmlFormat::Compact,
+    );
+    html.push('\n');
+}
+
+fn write_open_tag(html: &mut String, tag: &str, attributes: &[(String, String)]) {
+    html.push_str(&format!("<{}", tag));
+    for (key, value) in attributes {
+        if !BOOLEAN_ATTRIBUTES.contains(&key.as_str()) {
+            html.push_str(&format!(" {}=\"{}\"", key, escape_attribute(value)));
+        } else if value != "false" {
+            html.push_str(&format!(" {}", key));
+        }
+    }
+    html.push('>');
+}
+
+/// Replaces every run of whitespace with a single space.
+fn collapse_whitespace(text: &str) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut in_whitespace = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !in_whitespace {
+                collapsed.push(' ');
+            }
+            in_whitespace = true;
+        } else {
+            collapsed.push(c);
+            in_whitespace = false;
+        }
+    }
+    collapsed
+}
+
+/// Escapes text so that it is displayed as is between HTML tags.
+pub fn escape_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Escapes text so that it can be placed in a double-quoted attribute value.
+pub fn escape_attribute(value: &str) -> String {
+    escape_text(value).replace('"', "&quot;")
+}
+
+/// Keeps the content of a `<tag>` element, for `script` or `style`, from closing it early. `<\/` means `</` both in
+/// Javascript strings and in CSS.
+fn escape_raw_text(text: &str, tag: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(i) = rest.find("</") {
+        let after = &rest[i + 2..];
+        let closes = after
+            .get(..tag.len())
+            .is_some_and(|name| name.eq_ignore_ascii_case(tag));
+        escaped.push_str(&rest[..i]);
+        escaped.push_str(if closes { "<\\/" } else { "</" });
+        rest = after;
+    }
+    escaped.push_str(rest);
+    escaped
+}
+
 #[derive(Clone, Debug)]
 pub enum DomElement {
+    /// Text content, escaped when rendered.
     Text(String),
+    /// Trusted markup, rendered verbatim.
+    RawHtml(String),
     Element {
         tag: String,
         attributes: Vec<(String, String)>,
@@ -36,55 +279,224 @@ pub enum DomElement {
     },
 }
 
-impl DomElement {
-    pub fn script(body: &str) -> Self {
-        DomElement::Element {
-            tag: "script".to_string(),
-            attributes: vec![],
-            children: Dom(vec![DomElement::Text(body.to_string())]),
+/// Starts building a `<tag>` element, e.g. `el("input").attr("type", "text").id("x")`.
+pub fn el(tag: &str) -> DomElement {
+    DomElement::Element {
+        tag: tag.to_string(),
+        attributes: vec![],
+        children: Dom(vec![]),
+    }
+}
+
+/// A text node, escaped when rendered.
+pub fn text(text: impl Into<String>) -> DomElement {
+    DomElement::Text(text.into())
+}
+
+/// A node of trusted markup, rendered verbatim.
+pub fn raw(html: impl Into<String>) -> DomElement {
+    DomElement::RawHtml(html.into())
+}
+
+/// Parses markup made of a single `<script>` or `<style>` element, or a single void element such as `<link>`, the
+/// shape raw blocks of scripts and styles come in. The content of the element is kept as raw HTML.
+pub fn parse_leaf(html: &str) -> Option<DomElement> {
+    let rest = html.trim().strip_prefix('<')?;
+    let name_end = rest.find(|c: char| !c.is_ascii_alphanumeric())?;
+    let tag = rest[..name_end].to_ascii_lowercase();
+    let (attributes, rest) = parse_attributes(&rest[name_end..])?;
+
+    if VOID_ELEMENTS.contains(&tag.as_str()) {
+        // Some writers close void elements anyway
+        let rest = rest.trim();
+        let closed = rest.is_empty() || rest.eq_ignore_ascii_case(&format!("</{}>", tag));
+        return closed.then(|| el(&tag).attrs(attributes));
+    }
+    if !RAW_TEXT_ELEMENTS.contains(&tag.as_str()) {
+        return None;
+    }
+
+    let close = format!("</{}>", tag);
+    let content_end = rest.len().checked_sub(close.len())?;
+    if !rest.is_char_boundary(content_end) || !rest[content_end..].eq_ignore_ascii_case(&close) {
+        return None;
+    }
+    let content = &rest[..content_end];
+    if content.to_ascii_lowercase().contains(&format!("</{}", tag)) {
+        return None;
+    }
+
+    let element = el(&tag).attrs(attributes);
+    Some(if content.is_empty() {
+        element
+    } else {
+        element.child(raw(content))
+    })
+}
+
+/// Parses the attributes of an opening tag, up to and including its `>`, returning them and the markup after it.
+fn parse_attributes(tag: &str) -> Option<(Vec<(String, String)>, &str)> {
+    let mut attributes = vec![];
+    let mut rest = tag;
+    loop {
+        rest = rest.trim_start();
+        if let Some(after) = rest.strip_prefix('>').or_else(|| rest.strip_prefix("/>")) {
+            return Some((attributes, after));
         }
+
+        let name_end =
+            rest.find(|c: char| c.is_whitespace() || c == '=' || c == '>' || c == '/')?;
+        if name_end == 0 {
+            return None;
+        }
+        let name = rest[..name_end].to_ascii_lowercase();
+        rest = rest[name_end..].trim_start();
+
+        let value = match rest.strip_prefix('=') {
+            Some(after) => {
+                let after = after.trim_start();
+                let (value, after) = match after.chars().next()? {
+                    quote @ ('"' | '\'') => {
+                        let end = after[1..].find(quote)? + 1;
+                        (&after[1..end], &after[end + 1..])
+                    }
+                    _ => {
+                        let end = after.find(|c: char| c.is_whitespace() || c == '>')?;
+                        after.split_at(end)
+                    }
+                };
+                rest = after;
+                unescape_attribute(value)
+            }
+            None => String::new(),
+        };
+        attributes.push((name, value));
     }
 }
 
+/// Reverses [`escape_attribute`], along with the other common character references.
+fn unescape_attribute(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
 impl DomElement {
-    pub fn with_attr(&self, key: &str, value: &str) -> Self {
+    pub fn script(body: &str) -> Self {
+        el("script").text(body)
+    }
+
+    /// A script running `body` once the document is loaded, or right away if it already is.
+    pub fn deferred_script(body: &str) -> Self {
+        DomElement::script(&format!(
+            r#"
+(function (run) {{
+    if (document.readyState === "loading") {{
+        document.addEventListener("DOMContentLoaded", run);
+    }} else {{
+        run();
+    }}
+}})(function () {{
+{}
+}});
+"#,
+            body.trim_matches('\n')
+        ))
+    }
+
+    /// The content of a `<script>` or `<style>` element, as [`Dom::to_html`] writes it in `format`.
+    pub fn inner_html(&self, format: HtmlFormat) -> String {
+        let mut html = String::new();
+        if let DomElement::Element { tag, children, .. } = self {
+            // Pretty-printing never changes the content of scripts and styles
+            let format = match format {
+                HtmlFormat::Pretty => HtmlFormat::Compact,
+                format => format,
+            };
+            let context = child_context(tag, TextContext::Flow);
+            write_elements(&mut html, &children.0, context, format);
+        }
+        html
+    }
+
+    /// Sets the attribute `key`, replacing its previous value.
+    pub fn attr(mut self, key: &str, value: impl Into<String>) -> Self {
+        let value = value.into();
+        let attributes = self.attributes_mut();
+        match attributes.iter_mut().find(|(k, _)| k == key) {
+            Some((_, v)) => *v = value,
+            None => attributes.push((key.to_string(), value)),
+        }
+        self
+    }
+
+    /// Sets every attribute of `attributes`, in order.
+    pub fn attrs(self, attributes: impl IntoIterator<Item = (String, String)>) -> Self {
+        attributes
+            .into_iter()
+            .fold(self, |element, (key, value)| element.attr(&key, value))
+    }
+
+    /// Sets the boolean attribute `key`, such as `checked`, when `on` is true.
+    pub fn flag(self, key: &str, on: bool) -> Self {
+        if on {
+            self.attr(key, "")
+        } else {
+            self
+        }
+    }
+
+    pub fn id(self, id: impl Into<String>) -> Self {
+        self.attr("id", id)
+    }
+
+    /// Adds `class` to the class list of the element.
+    pub fn class(mut self, class: &str) -> Self {
+        let attributes = self.attributes_mut();
+        match attributes.iter_mut().find(|(k, _)| k == "class") {
+            Some((_, classes)) if classes.split_whitespace().any(|c| c == class) => {}
+            Some((_, classes)) if classes.is_empty() => *classes = class.to_string(),
+            Some((_, classes)) => {
+                classes.push(' ');
+                classes.push_str(class);
+            }
+            None => attributes.push(("class".to_string(), class.to_string())),
+        }
+        self
+    }
+
+    pub fn child(mut self, child: DomElement) -> Self {
+        self.children_mut().0.push(child);
+        self
+    }
+
+    pub fn children(mut self, children: impl IntoIterator<Item = DomElement>) -> Self {
+        self.children_mut().0.extend(children);
+        self
+    }
+
+    /// Appends a text node to the children of the element.
+    pub fn text(self, content: impl Into<String>) -> Self {
+        self.child(text(content))
+    }
+
+    fn attributes_mut(&mut self) -> &mut Vec<(String, String)> {
         match self {
-            DomElement::Text(_) => panic!("Cannot add attributes to text nodes"),
-            DomElement::Element {
-                tag,
-                attributes,
-                children,
-            } => {
-                let mut attributes = attributes.clone();
-                if attributes.iter().any(|(k, _)| k == key) {
-                    attributes = attributes
-                        .iter()
-                        .map(|(k, v)| {
-                            if k == key {
-                                (k.clone(), value.to_string())
-                            } else {
-                                (k.clone(), v.clone())
-                            }
-                        })
-                        .collect();
-                } else {
-                    attributes.push((key.to_string(), value.to_string()));
-                }
+            DomElement::Element { attributes, .. } => attributes,
+            DomElement::Text(_) | DomElement::RawHtml(_) => {
+                panic!("Cannot add attributes to text nodes")
+            }
+        }
+    }
 
-                DomElement::Element {
-                    tag: tag.clone(),
-                    attributes: attributes
-                        .iter()
-                        .map(|(k, v)| {
-                            if k == key {
-                                (k.clone(), value.to_string())
-                            } else {
-                                (k.clone(), v.clone())
-                            }
-                        })
-                        .collect(),
-                    children: children.clone(),
-                }
+    fn children_mut(&mut self) -> &mut Dom {
+        match self {
+            DomElement::Element { children, .. } => children,
+            DomElement::Text(_) | DomElement::RawHtml(_) => {
+                panic!("Cannot add children to text nodes")
             }
         }
     }
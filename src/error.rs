@@ -0,0 +1,294 @@
+use std::fmt;
+
+/// A byte range within a source text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    /// Moves the span forward by `offset` bytes, e.g. from a code block into the file containing it.
+    pub fn shift(self, offset: usize) -> Self {
+        Span::new(self.start + offset, self.end + offset)
+    }
+}
+
+impl From<pest::Span<'_>> for Span {
+    fn from(span: pest::Span<'_>) -> Self {
+        Span::new(span.start(), span.end())
+    }
+}
+
+/// An error in a DCL code block, with a span relative to the start of the block.
+#[derive(Clone, Debug)]
+pub struct DclError {
+    pub message: String,
+    pub span: Span,
+}
+
+impl DclError {
+    pub fn new(message: impl Into<String>, span: Span) -> Self {
+        DclError {
+            message: message.into(),
+            span,
+        }
+    }
+
+    /// Renders the error as a compiler-style diagnostic, with the span shifted by `offset` into `source`.
+    pub fn report(&self, name: &str, source: &str, offset: usize) -> String {
+        diagnostic(&self.message, name, source, self.span.shift(offset))
+    }
+}
+
+/// Formats `message` followed by the line of `source` containing `span`, with the span underlined.
+fn diagnostic(message: &str, name: &str, source: &str, span: Span) -> String {
+    let start = span.start.min(source.len());
+
+    let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = source[start..]
+        .find('\n')
+        .map_or(source.len(), |i| start + i);
+    let line_number = source[..start].matches('\n').count() + 1;
+    let column = source[line_start..start].chars().count() + 1;
+
+    let line = &source[line_start..line_end];
+    let width = source[start..line_end]
+        .char_indices()
+        .take_while(|(i, _)| start + i < span.end)
+        .count()
+        .max(1);
+    let gutter = " ".repeat(line_number.to_string().len());
+
+    format!(
+        "{}\n{gutter}--> {}:{}:{}\n{gutter} |\n{} | {}\n{gutter} | {}{}",
+        message,
+        name,
+        line_number,
+        column,
+        line_number,
+        line,
+        " ".repeat(column - 1),
+        "^".repeat(width),
+    )
+}
+
+impl fmt::Display for DclError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for DclError {}
+
+impl From<pest::error::Error<crate::dcl::Rule>> for DclError {
+    fn from(error: pest::error::Error<crate::dcl::Rule>) -> Self {
+        let span = match error.location {
+            pest::error::InputLocation::Pos(pos) => Span::new(pos, pos + 1),
+            pest::error::InputLocation::Span((start, end)) => Span::new(start, end),
+        };
+        DclError::new(error.variant.message(), span)
+    }
+}
+
+/// What went wrong in a code block.
+#[derive(Clone, Debug)]
+pub enum BlockErrorKind {
+    /// The DCL source of the block is invalid.
+    Dcl(DclError),
+    /// An attribute of the block, such as the `rate` of a mermaid diagram, has an unusable value.
+    Attribute {
+        key: String,
+        value: String,
+        message: String,
+    },
+}
+
+impl fmt::Display for BlockErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlockErrorKind::Dcl(error) => write!(f, "{}", error),
+            BlockErrorKind::Attribute { message, .. } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<DclError> for BlockErrorKind {
+    fn from(error: DclError) -> Self {
+        BlockErrorKind::Dcl(error)
+    }
+}
+
+/// An error raised while rendering one of the code blocks of a document.
+#[derive(Clone, Debug)]
+pub struct BlockError {
+    /// Source of the failing code block.
+    pub code: String,
+    /// How many code blocks with the same source precede the failing one in the document.
+    pub occurrence: usize,
+    pub error: BlockErrorKind,
+}
+
+impl BlockError {
+    /// Renders the error against the files the document was read from, given as `(name, contents)` pairs.
+    ///
+    /// Pandoc does not keep source positions, so the block is found by searching for its source in the files.
+    /// When it cannot be found, the diagnostic points into the code block itself.
+    pub fn report(&self, sources: &[(String, String)]) -> String {
+        let mut remaining = self.occurrence;
+        for (name, source) in sources {
+            for (offset, _) in source.match_indices(&self.code) {
+                if remaining == 0 {
+                    return self.report_at(name, source, offset);
+                }
+                remaining -= 1;
+            }
+        }
+        self.to_string()
+    }
+
+    fn report_at(&self, name: &str, source: &str, offset: usize) -> String {
+        match &self.error {
+            BlockErrorKind::Dcl(error) => error.report(name, source, offset),
+            BlockErrorKind::Attribute {
+                key,
+                value,
+                message,
+            } => {
+                // Attributes live on the opening fence, the line right before the code
+                let fence_end = offset.saturating_sub(1);
+                let fence_start = source[..fence_end].rfind('\n').map_or(0, |i| i + 1);
+                let fence = &source[fence_start..fence_end];
+                let span = match fence.find(&format!("{}=", key)) {
+                    Some(i) => {
+                        let start = fence_start + i;
+                        Span::new(start, start + key.len() + 1 + value.len())
+                    }
+                    None => Span::new(fence_start, fence_end),
+                };
+                diagnostic(message, name, source, span)
+            }
+        }
+    }
+}
+
+impl fmt::Display for BlockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.error {
+            BlockErrorKind::Dcl(error) => {
+                write!(f, "{}", error.report("<code block>", &self.code, 0))
+            }
+            BlockErrorKind::Attribute { .. } => write!(f, "{}", self.error),
+        }
+    }
+}
+
+impl std::error::Error for BlockError {}
+
+/// An invalid value in the front matter of an article.
+#[derive(Clone, Debug)]
+pub struct MetaError {
+    /// Dotted path of the offending field, e.g. `devy.mermaid-rate`.
+    pub key: String,
+    pub message: String,
+}
+
+impl MetaError {
+    pub fn new(key: impl Into<String>, message: impl Into<String>) -> Self {
+        MetaError {
+            key: key.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Renders the error against the files the document was read from, pointing at the field if it can be found.
+    pub fn report(&self, sources: &[(String, String)]) -> String {
+        let field = self.key.rsplit('.').next().unwrap_or(&self.key);
+        let pattern = format!("{}:", field);
+        for (name, source) in sources {
+            if let Some(offset) = source.find(&pattern) {
+                let span = Span::new(offset, offset + field.len());
+                return diagnostic(&self.to_string(), name, source, span);
+            }
+        }
+        self.to_string()
+    }
+}
+
+impl fmt::Display for MetaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "invalid front matter field `{}`: {}",
+            self.key, self.message
+        )
+    }
+}
+
+impl std::error::Error for MetaError {}
+
+/// Anything that can go wrong while rendering a document.
+#[derive(Clone, Debug)]
+pub enum Error {
+    Block(BlockError),
+    Meta(MetaError),
+}
+
+impl Error {
+    /// Renders the error against the files the document was read from, given as `(name, contents)` pairs.
+    pub fn report(&self, sources: &[(String, String)]) -> String {
+        match self {
+            Error::Block(error) => error.report(sources),
+            Error::Meta(error) => error.report(sources),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Block(error) => write!(f, "{}", error),
+            Error::Meta(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<BlockError> for Error {
+    fn from(error: BlockError) -> Self {
+        Error::Block(error)
+    }
+}
+
+impl From<MetaError> for Error {
+    fn from(error: MetaError) -> Self {
+        Error::Meta(error)
+    }
+}
+
+/// An invalid page template.
+#[derive(Clone, Debug)]
+pub struct TemplateError {
+    pub message: String,
+}
+
+impl TemplateError {
+    pub fn new(message: impl Into<String>) -> Self {
+        TemplateError {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for TemplateError {}
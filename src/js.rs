@@ -0,0 +1,774 @@
+//! Just enough of a Javascript lexer to rewrite the identifiers of DCL function bodies.
+//!
+//! Bodies are tokenized so that strings, comments, regular expressions and property names are left alone, and the
+//! names bound inside a body (by `let`, `const`, `var`, functions, arrow functions and `catch`, including
+//! destructuring patterns) shadow the variables of the function for the rest of their scope.
+
+use std::collections::{HashMap, HashSet};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TokenKind {
+    Identifier,
+    Number,
+    String,
+    /// A piece of a template literal, from a backtick or the `}` closing a substitution, to a backtick or `${`.
+    Template,
+    Regex,
+    Punctuator,
+    Comment,
+    Whitespace,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Token {
+    kind: TokenKind,
+    start: usize,
+    end: usize,
+}
+
+/// Rewrites the free identifiers of `source` for which `replace` returns a replacement.
+///
+/// Property names such as `b` in `a.b` or `{ b: 1 }` are not identifiers, and shorthand properties keep their name,
+/// so `{ x }` becomes `{ x: <replacement> }`.
+pub fn replace_free_identifiers(
+    source: &str,
+    replace: impl Fn(&str) -> Option<String>,
+) -> Result<String, String> {
+    let tokens = tokenize(source)?;
+    let significant: Vec<Token> = tokens
+        .iter()
+        .copied()
+        .filter(|t| !matches!(t.kind, TokenKind::Comment | TokenKind::Whitespace))
+        .collect();
+    let text = |token: Option<&Token>| token.map(|t| &source[t.start..t.end]);
+    let closing = matching_brackets(source, &significant);
+    // Identifiers of destructuring patterns, which are either bound or the names of properties
+    let mut skipped: HashSet<usize> = HashSet::new();
+
+    let mut replacements: Vec<(Token, String)> = vec![];
+    let mut brackets: Vec<Bracket> = vec![];
+    let mut scopes = vec![Scope {
+        names: vec![],
+        depth: 0,
+        expression: false,
+    }];
+    // Names bound by the next block, and by the next arrow
+    let mut pending: Vec<String> = vec![];
+    let mut arrow_params: Vec<String> = vec![];
+    // The depth of the parameter list being read, and whether it belongs to an arrow function
+    let mut params: Option<(usize, bool)> = None;
+    let mut collected: Vec<String> = vec![];
+    // The depth of the `let`, `const` or `var` declaration being read, whether a name is expected next, and whether it
+    // is a `var` of a loop header, binding outside of the loop
+    let mut declaration: Option<usize> = None;
+    let mut declaring = false;
+    let mut hoisted = false;
+    // Whether the parameters of a function are expected, and whether its name only binds within its own body
+    let mut function = false;
+    let mut named_expression = false;
+    // Whether the parentheses of a `for` are expected, and the depth and scope of each loop header being read, whose
+    // `let` and `const` only bind within the loop
+    let mut loop_header = false;
+    let mut loop_headers: Vec<(usize, usize)> = vec![];
+
+    for (index, token) in significant.iter().enumerate() {
+        let current = &source[token.start..token.end];
+        let previous = text(index.checked_sub(1).and_then(|i| significant.get(i)));
+        let next = text(significant.get(index + 1));
+        if skipped.contains(&index) {
+            continue;
+        }
+
+        match token.kind {
+            TokenKind::Identifier => {
+                if matches!(previous, Some(".") | Some("?.")) {
+                    continue;
+                }
+                match current {
+                    "let" | "const" | "var" => {
+                        declaration = Some(brackets.len());
+                        declaring = true;
+                        hoisted = current == "var"
+                            && loop_headers
+                                .last()
+                                .is_some_and(|&(depth, _)| depth == brackets.len());
+                        continue;
+                    }
+                    "for" => {
+                        loop_header = true;
+                        continue;
+                    }
+                    // `catch { ... }` binds nothing
+                    "catch" if next != Some("(") => continue,
+                    "function" | "catch" => {
+                        // Only a function declaration binds its name outside of its body
+                        let statement = matches!(previous, Some(";") | Some("{") | Some("}"))
+                            && !brackets.last().is_some_and(|b| b.object);
+                        named_expression = !statement;
+                        function = true;
+                        continue;
+                    }
+                    _ => {}
+                }
+                if function && named_expression {
+                    pending.push(current.to_string());
+                    continue;
+                }
+                if declaring || function {
+                    let hoisted = std::mem::take(&mut declaring) && hoisted;
+                    declaration_scope(&mut scopes, hoisted)
+                        .names
+                        .push(current.to_string());
+                    continue;
+                }
+                if let Some((depth, _)) = params {
+                    if depth == brackets.len()
+                        && matches!(previous, Some("(") | Some(",") | Some("..."))
+                    {
+                        collected.push(current.to_string());
+                        continue;
+                    }
+                }
+                if next == Some("=>") {
+                    arrow_params = vec![current.to_string()];
+                    continue;
+                }
+
+                let in_object = brackets.last().is_some_and(|b| b.object);
+                let starts_property = matches!(previous, Some("{") | Some(","));
+                if in_object && starts_property && next == Some(":") {
+                    continue;
+                }
+                if in_object && starts_property && next == Some("(") {
+                    // A method, whose parameters are read like a function's
+                    function = true;
+                    continue;
+                }
+                if scopes.iter().any(|s| s.names.iter().any(|n| n == current)) {
+                    continue;
+                }
+                if let Some(replacement) = replace(current) {
+                    let shorthand =
+                        in_object && starts_property && matches!(next, Some(",") | Some("}"));
+                    let replacement = if shorthand {
+                        format!("{}: {}", current, replacement)
+                    } else {
+                        replacement
+                    };
+                    replacements.push((*token, replacement));
+                }
+            }
+            TokenKind::Punctuator => match current {
+                "(" => {
+                    brackets.push(Bracket { object: false });
+                    if std::mem::take(&mut loop_header) {
+                        loop_headers.push((brackets.len(), scopes.len()));
+                        scopes.push(Scope {
+                            names: vec![],
+                            depth: brackets.len(),
+                            expression: false,
+                        });
+                    } else if function {
+                        function = false;
+                        params = Some((brackets.len(), false));
+                    } else if closing
+                        .get(&index)
+                        .is_some_and(|&end| text(significant.get(end + 1)) == Some("=>"))
+                    {
+                        params = Some((brackets.len(), true));
+                    }
+                }
+                "[" | "{" if declaring || starts_parameter(params, brackets.len(), previous) => {
+                    let names = if declaring {
+                        declaring = false;
+                        &mut declaration_scope(&mut scopes, hoisted).names
+                    } else {
+                        &mut collected
+                    };
+                    binding_pattern(source, &significant, &closing, index, names, &mut skipped);
+                    // Braces of a pattern are not a block
+                    brackets.push(Bracket {
+                        object: current == "{",
+                    });
+                }
+                "[" => brackets.push(Bracket { object: false }),
+                "{" => {
+                    let object = match index.checked_sub(1).and_then(|i| significant.get(i)) {
+                        None => true,
+                        Some(t) if t.kind == TokenKind::Identifier => {
+                            matches!(previous, Some("return") | Some("typeof"))
+                        }
+                        Some(t) if t.kind == TokenKind::Punctuator => !matches!(
+                            previous,
+                            Some(")") | Some("=>") | Some("{") | Some("}") | Some(";")
+                        ),
+                        Some(_) => true,
+                    };
+                    brackets.push(Bracket { object });
+                    if !object {
+                        scopes.push(Scope {
+                            names: std::mem::take(&mut pending),
+                            depth: brackets.len(),
+                            expression: false,
+                        });
+                    }
+                }
+                ")" | "]" | "}" => {
+                    if let Some((depth, arrow)) = params {
+                        if current == ")" && depth == brackets.len() {
+                            params = None;
+                            if arrow {
+                                arrow_params = std::mem::take(&mut collected);
+                            } else {
+                                pending.append(&mut collected);
+                            }
+                        }
+                    }
+                    let header = loop_headers
+                        .last()
+                        .filter(|&&(depth, _)| current == ")" && depth == brackets.len())
+                        .map(|&(_, scope)| scope);
+                    brackets.pop();
+                    if declaration.is_some_and(|depth| depth > brackets.len()) {
+                        declaration = None;
+                    }
+                    if let Some(scope) = header {
+                        // The names of the header bind in the body, a block or a single statement
+                        loop_headers.pop();
+                        scopes.truncate(scope + 1);
+                        let names = scopes.pop().unwrap().names;
+                        if next == Some("{") {
+                            pending.extend(names);
+                        } else {
+                            scopes.push(Scope {
+                                names,
+                                depth: brackets.len(),
+                                expression: true,
+                            });
+                        }
+                    }
+                    close_scopes(&mut scopes, brackets.len(), false);
+                }
+                "," | ";" => {
+                    declaring = current == "," && declaration == Some(brackets.len());
+                    if current == ";" {
+                        declaration = None;
+                    }
+                    close_scopes(&mut scopes, brackets.len(), true);
+                }
+                "=>" => {
+                    let params = std::mem::take(&mut arrow_params);
+                    if next == Some("{") {
+                        pending = params;
+                    } else {
+                        scopes.push(Scope {
+                            names: params,
+                            depth: brackets.len(),
+                            expression: true,
+                        });
+                    }
+                }
+                _ => declaring = false,
+            },
+            TokenKind::Template => {
+                if current.starts_with('}') {
+                    brackets.pop();
+                    close_scopes(&mut scopes, brackets.len(), false);
+                }
+                if current.ends_with("${") {
+                    brackets.push(Bracket { object: false });
+                }
+            }
+            _ => declaring = false,
+        }
+    }
+
+    let mut result = String::with_capacity(source.len());
+    let mut position = 0;
+    for (token, replacement) in replacements {
+        result.push_str(&source[position..token.start]);
+        result.push_str(&replacement);
+        position = token.end;
+    }
+    result.push_str(&source[position..]);
+    Ok(result)
+}
+
+/// An open bracket, and whether it is the brace of an object literal rather than a block.
+struct Bracket {
+    object: bool,
+}
+
+/// Names bound within a body, until the brackets are closed below `depth`, or for the body of an arrow function
+/// without braces, until the expression ends.
+struct Scope {
+    names: Vec<String>,
+    depth: usize,
+    expression: bool,
+}
+
+/// Closes the scopes ending at the current `depth`, including expression scopes ending with a `,` or `;`.
+fn close_scopes(scopes: &mut Vec<Scope>, depth: usize, separator: bool) {
+    while scopes.len() > 1 {
+        let scope = scopes.last().unwrap();
+        if scope.depth > depth || (separator && scope.expression && scope.depth == depth) {
+            scopes.pop();
+        } else {
+            break;
+        }
+    }
+}
+
+/// The scope a declaration binds its names in, the one enclosing a loop header for a `var` declared in it.
+fn declaration_scope(scopes: &mut [Scope], hoisted: bool) -> &mut Scope {
+    let index = scopes.len() - 1 - usize::from(hoisted);
+    &mut scopes[index]
+}
+
+/// Whether the token after `previous` starts a parameter of the parameter list being read.
+fn starts_parameter(params: Option<(usize, bool)>, depth: usize, previous: Option<&str>) -> bool {
+    params.is_some_and(|(params, _)| params == depth)
+        && matches!(previous, Some("(") | Some(",") | Some("..."))
+}
+
+/// The index of the bracket closing each `(`, `[`, `{` and template substitution, by their indices in `tokens`.
+fn matching_brackets(source: &str, tokens: &[Token]) -> HashMap<usize, usize> {
+    let mut closing = HashMap::new();
+    let mut open = vec![];
+    for (index, token) in tokens.iter().enumerate() {
+        let text = &source[token.start..token.end];
+        match token.kind {
+            TokenKind::Punctuator if matches!(text, "(" | "[" | "{") => open.push(index),
+            TokenKind::Punctuator if matches!(text, ")" | "]" | "}") => {
+                if let Some(start) = open.pop() {
+                    closing.insert(start, index);
+                }
+            }
+            TokenKind::Template => {
+                if text.starts_with('}') {
+                    if let Some(start) = open.pop() {
+                        closing.insert(start, index);
+                    }
+                }
+                if text.ends_with("${") {
+                    open.push(index);
+                }
+            }
+            _ => {}
+        }
+    }
+    closing
+}
+
+/// Collects the names bound by the destructuring pattern opening at `start` into `names`, and the identifiers of
+/// the pattern that must not be rewritten, the bound names and the properties they are read from, into `skipped`.
+/// Default values and computed property names are left to be rewritten like any expression.
+fn binding_pattern(
+    source: &str,
+    tokens: &[Token],
+    closing: &HashMap<usize, usize>,
+    start: usize,
+    names: &mut Vec<String>,
+    skipped: &mut HashSet<usize>,
+) {
+    let text = |index: usize| tokens.get(index).map(|t| &source[t.start..t.end]);
+    let is_identifier = |index: usize| {
+        tokens
+            .get(index)
+            .is_some_and(|t| t.kind == TokenKind::Identifier)
+    };
+    let end = closing.get(&start).copied().unwrap_or(tokens.len());
+    let object = text(start) == Some("{");
+
+    let mut index = start + 1;
+    while index < end {
+        if matches!(text(index), Some(",") | Some("...")) {
+            index += 1;
+            continue;
+        }
+
+        // The property of `key: target` in object patterns
+        if object {
+            let key_end = match text(index) {
+                Some("[") => closing.get(&index).copied(),
+                _ => Some(index),
+            };
+            if let Some(key_end) = key_end.filter(|&k| text(k + 1) == Some(":")) {
+                if is_identifier(index) {
+                    skipped.insert(index);
+                }
+                index = key_end + 2;
+            }
+        }
+
+        // The target, a name or a nested pattern
+        if is_identifier(index) {
+            names.push(text(index).unwrap().to_string());
+            skipped.insert(index);
+            index += 1;
+        } else if matches!(text(index), Some("[") | Some("{")) {
+            binding_pattern(source, tokens, closing, index, names, skipped);
+            index = closing.get(&index).map_or(end, |&c| c + 1);
+        } else {
+            index += 1;
+            continue;
+        }
+
+        // A default value, up to the next element
+        if text(index) == Some("=") {
+            index += 1;
+            while index < end && text(index) != Some(",") {
+                index = closing.get(&index).copied().unwrap_or(index) + 1;
+            }
+        }
+    }
+}
+
+/// A Javascript string literal with the value `s`.
+pub fn string_literal(s: &str) -> String {
+    let mut literal = String::with_capacity(s.len() + 2);
+    literal.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\u{2028}' => literal.push_str("\\u2028"),
+            '\u{2029}' => literal.push_str("\\u2029"),
+            c => literal.push(c),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+fn is_identifier_start(byte: u8) -> bool {
+    byte.is_ascii_alphabetic() || byte == b'_' || byte == b'$' || byte >= 0x80
+}
+
+fn is_identifier_part(byte: u8) -> bool {
+    is_identifier_start(byte) || byte.is_ascii_digit()
+}
+
+/// Splits `source` into tokens, failing on unterminated strings, comments, template literals and regular expressions.
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let bytes = source.as_bytes();
+    let mut tokens: Vec<Token> = vec![];
+    // Whether each open brace is the `${` of a template literal
+    let mut braces: Vec<bool> = vec![];
+    let mut position = 0;
+
+    while position < bytes.len() {
+        let start = position;
+        let byte = bytes[position];
+        let rest = &source[position..];
+
+        let kind = if byte.is_ascii_whitespace() {
+            while position < bytes.len() && bytes[position].is_ascii_whitespace() {
+                position += 1;
+            }
+            TokenKind::Whitespace
+        } else if rest.starts_with("//") {
+            position = rest.find('\n').map_or(bytes.len(), |i| start + i);
+            TokenKind::Comment
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            let end = comment.find("*/").ok_or("unterminated comment")?;
+            position = start + 2 + end + 2;
+            TokenKind::Comment
+        } else if byte == b'"' || byte == b'\'' {
+            position = string_end(bytes, position, byte).ok_or("unterminated string")?;
+            TokenKind::String
+        } else if byte == b'`' {
+            position = template_end(bytes, position + 1, &mut braces)?;
+            TokenKind::Template
+        } else if byte == b'}' && braces.last() == Some(&true) {
+            braces.pop();
+            position = template_end(bytes, position + 1, &mut braces)?;
+            TokenKind::Template
+        } else if is_identifier_start(byte) {
+            while position < bytes.len() && is_identifier_part(bytes[position]) {
+                position += 1;
+            }
+            TokenKind::Identifier
+        } else if byte.is_ascii_digit()
+            || (byte == b'.' && rest[1..].starts_with(|c: char| c.is_ascii_digit()))
+        {
+            while position < bytes.len()
+                && (is_identifier_part(bytes[position]) || bytes[position] == b'.')
+            {
+                position += 1;
+            }
+            TokenKind::Number
+        } else if byte == b'/' && regex_allowed(source, &tokens) {
+            position = regex_end(bytes, position).ok_or("unterminated regular expression")?;
+            TokenKind::Regex
+        } else {
+            let punctuator = ["...", "=>", "?.", "++", "--"]
+                .iter()
+                .find(|p| rest.starts_with(**p))
+                // `a?.5:b` is a conditional, not an optional chain
+                .filter(|p| **p != "?." || !rest[2..].starts_with(|c: char| c.is_ascii_digit()));
+            position +=
+                punctuator.map_or(rest.chars().next().map_or(1, char::len_utf8), |p| p.len());
+            match byte {
+                b'{' => braces.push(false),
+                b'}' => {
+                    braces.pop();
+                }
+                _ => {}
+            }
+            TokenKind::Punctuator
+        };
+
+        tokens.push(Token {
+            kind,
+            start,
+            end: position,
+        });
+    }
+
+    Ok(tokens)
+}
+
+/// The end of the string literal starting at `start` with `quote`.
+fn string_end(bytes: &[u8], start: usize, quote: u8) -> Option<usize> {
+    let mut position = start + 1;
+    while position < bytes.len() {
+        match bytes[position] {
+            b'\\' => position += 2,
+            b'\n' => return None,
+            byte if byte == quote => return Some(position + 1),
+            _ => position += 1,
+        }
+    }
+    None
+}
+
+/// The end of a piece of template literal starting at `position`, right after a backtick or a closing `}`. A piece
+/// ending with `${` opens a substitution, which is recorded in `braces`.
+fn template_end(
+    bytes: &[u8],
+    mut position: usize,
+    braces: &mut Vec<bool>,
+) -> Result<usize, String> {
+    while position < bytes.len() {
+        match bytes[position] {
+            b'\\' => position += 2,
+            b'`' => return Ok(position + 1),
+            b'$' if bytes.get(position + 1) == Some(&b'{') => {
+                braces.push(true);
+                return Ok(position + 2);
+            }
+            _ => position += 1,
+        }
+    }
+    Err("unterminated template literal".to_string())
+}
+
+/// The end of the regular expression literal starting at `start`, including its flags.
+fn regex_end(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut position = start + 1;
+    let mut class = false;
+    loop {
+        match *bytes.get(position)? {
+            b'\\' => position += 1,
+            b'\n' => return None,
+            b'[' => class = true,
+            b']' => class = false,
+            b'/' if !class => break,
+            _ => {}
+        }
+        position += 1;
+    }
+    position += 1;
+    while position < bytes.len() && is_identifier_part(bytes[position]) {
+        position += 1;
+    }
+    Some(position)
+}
+
+/// Whether a `/` after `tokens` starts a regular expression rather than being a division.
+fn regex_allowed(source: &str, tokens: &[Token]) -> bool {
+    let previous = tokens
+        .iter()
+        .rev()
+        .find(|t| !matches!(t.kind, TokenKind::Comment | TokenKind::Whitespace));
+    match previous {
+        None => true,
+        Some(token) => {
+            let text = &source[token.start..token.end];
+            match token.kind {
+                TokenKind::Identifier => {
+                    matches!(
+                        text,
+                        "return" | "typeof" | "case" | "do" | "else" | "in" | "of" | "new" | "void"
+                    )
+                }
+                // Postfix increments and decrements end an expression, like closing brackets
+                TokenKind::Punctuator => !matches!(text, ")" | "]" | "}" | "++" | "--"),
+                TokenKind::Template => text.ends_with("${"),
+                _ => false,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Rewrites the variables `c`, `n` and `x` into `<c>`, `<n>` and `<x>`.
+    fn rewrite(source: &str) -> String {
+        replace_free_identifiers(source, |name| {
+            matches!(name, "c" | "n" | "x").then(|| format!("<{}>", name))
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn rewrites_free_identifiers() {
+        assert_eq!(rewrite("x + n * 2"), "<x> + <n> * 2");
+        assert_eq!(rewrite("Math.max(x, nx)"), "Math.max(<x>, nx)");
+    }
+
+    #[test]
+    fn leaves_strings_comments_and_regular_expressions() {
+        assert_eq!(rewrite(r#""x" + 'x\'x' + x"#), r#""x" + 'x\'x' + <x>"#);
+        assert_eq!(rewrite("x /* x */ + 1 // x"), "<x> /* x */ + 1 // x");
+        assert_eq!(
+            rewrite(r#"x.replace(/x[/]/g, "")"#),
+            r#"<x>.replace(/x[/]/g, "")"#
+        );
+        assert_eq!(rewrite("x / 2 / n"), "<x> / 2 / <n>");
+        assert_eq!(rewrite("x++ / 2"), "<x>++ / 2");
+        assert_eq!(rewrite("x-- / n"), "<x>-- / <n>");
+    }
+
+    #[test]
+    fn rewrites_template_substitutions() {
+        assert_eq!(rewrite("`x ${x} ${`${n}`}`"), "`x ${<x>} ${`${<n>}`}`");
+        assert_eq!(rewrite("`${ {x} }`"), "`${ {x: <x>} }`");
+    }
+
+    #[test]
+    fn leaves_property_names() {
+        assert_eq!(rewrite("o.x + o?.x + x.length"), "o.x + o?.x + <x>.length");
+        assert_eq!(rewrite("({ x: x, n })"), "({ x: <x>, n: <n> })");
+        assert_eq!(
+            rewrite("({ c(n) { return n } }).c(x)"),
+            "({ c(n) { return n } }).c(<x>)"
+        );
+    }
+
+    #[test]
+    fn leaves_names_bound_in_the_body() {
+        assert_eq!(rewrite("xs.map(x => x * n)"), "xs.map(x => x * <n>)");
+        assert_eq!(
+            rewrite("((c, n) => c + n)(x, 1)"),
+            "((c, n) => c + n)(<x>, 1)"
+        );
+        assert_eq!(
+            rewrite("(() => { let x = 1, n = 2; return x + n + c; })() + x"),
+            "(() => { let x = 1, n = 2; return x + n + <c>; })() + <x>"
+        );
+        assert_eq!(
+            rewrite("[1].map((...x) => x) + x"),
+            "[1].map((...x) => x) + <x>"
+        );
+        assert_eq!(
+            rewrite("(() => { try { x } catch (x) { x } })()"),
+            "(() => { try { <x> } catch (x) { x } })()"
+        );
+        assert_eq!(
+            rewrite("(() => { try { n } catch { return x } })()"),
+            "(() => { try { <n> } catch { return <x> } })()"
+        );
+        assert_eq!(rewrite("p.catch(x => x) + n"), "p.catch(x => x) + <n>");
+    }
+
+    #[test]
+    fn binds_loop_declarations_in_the_loop() {
+        assert_eq!(
+            rewrite("(() => { for (const x of c) { n += x } return x })()"),
+            "(() => { for (const x of <c>) { <n> += x } return <x> })()"
+        );
+        assert_eq!(
+            rewrite("(() => { for (let n = 0; n < 3; n++) c += n; return n })()"),
+            "(() => { for (let n = 0; n < 3; n++) <c> += n; return <n> })()"
+        );
+        assert_eq!(
+            rewrite("(() => { for (const [c, n] of x) {} return c + n })()"),
+            "(() => { for (const [c, n] of <x>) {} return <c> + <n> })()"
+        );
+        assert_eq!(
+            rewrite("(() => { for (var n = 0; n < x; n++) {} return n })()"),
+            "(() => { for (var n = 0; n < <x>; n++) {} return n })()"
+        );
+        assert_eq!(
+            rewrite("(() => { for (var n of []) {} function c() {} return c })() + c"),
+            "(() => { for (var n of []) {} function c() {} return c })() + <c>"
+        );
+    }
+
+    #[test]
+    fn binds_function_names_where_they_are_visible() {
+        assert_eq!(
+            rewrite("(function x() { return x })() + x"),
+            "(function x() { return x })() + <x>"
+        );
+        assert_eq!(
+            rewrite("(() => { function x() { return 1 } return x() })() + n"),
+            "(() => { function x() { return 1 } return x() })() + <n>"
+        );
+        assert_eq!(
+            rewrite("(function (x) { return x + n })(x)"),
+            "(function (x) { return x + <n> })(<x>)"
+        );
+    }
+
+    #[test]
+    fn leaves_names_bound_by_destructuring() {
+        assert_eq!(
+            rewrite("pairs.map(([c, n]) => c + n)"),
+            "pairs.map(([c, n]) => c + n)"
+        );
+        assert_eq!(
+            rewrite("xs.map(({c}) => c) + c"),
+            "xs.map(({c}) => c) + <c>"
+        );
+        assert_eq!(
+            rewrite("xs.map(({ a: c, b: [n] }) => c + n)"),
+            "xs.map(({ a: c, b: [n] }) => c + n)"
+        );
+        assert_eq!(
+            rewrite("xs.map(({ c = x }) => c)"),
+            "xs.map(({ c = <x> }) => c)"
+        );
+        assert_eq!(
+            rewrite("(() => { let [a, n] = x; return n; })() + n"),
+            "(() => { let [a, n] = <x>; return n; })() + <n>"
+        );
+        assert_eq!(
+            rewrite("(() => { const { c, x: [n] } = o; return c + n + x; })()"),
+            "(() => { const { c, x: [n] } = o; return c + n + <x>; })()"
+        );
+        assert_eq!(
+            rewrite("(function ({ c }, [n]) { return c + n + x })(o, a)"),
+            "(function ({ c }, [n]) { return c + n + <x> })(o, a)"
+        );
+    }
+
+    #[test]
+    fn fails_on_unterminated_literals() {
+        assert!(replace_free_identifiers(r#""x"#, |_| None).is_err());
+        assert!(replace_free_identifiers("`${x}", |_| None).is_err());
+        assert!(replace_free_identifiers("/* x", |_| None).is_err());
+        assert!(replace_free_identifiers("a(/x)", |_| None).is_err());
+    }
+
+    #[test]
+    fn writes_string_literals() {
+        assert_eq!(string_literal("a \"b\"\n\\"), r#""a \"b\"\n\\""#);
+    }
+}
@@ -0,0 +1,24 @@
+//! Devy renders markdown articles with interactive components through Pandoc.
+//!
+//! Code blocks written in DCL (Declarative Component Language) become HTML components wired together with
+//! Javascript, mermaid blocks become diagrams and script blocks are embedded into the page. The [`render`]
+//! module exposes these transformations on `pandoc_ast` documents, [`template`] places rendered articles into full
+//! HTML documents, and [`dcl`] is the language itself.
+
+pub mod csp;
+pub mod dcl;
+pub mod dom;
+pub mod error;
+pub mod js;
+pub mod meta;
+pub mod options;
+pub mod render;
+pub mod template;
+pub mod theme;
+
+pub use dcl::{interpret_dcl, parse_dcl};
+pub use error::{BlockError, BlockErrorKind, DclError, Error, MetaError, TemplateError};
+pub use options::{Options, OptionsLayer};
+pub use render::{render, render_code_block};
+pub use template::{Page, Template};
+pub use theme::Theme;
@@ -0,0 +1,103 @@
+use pandoc_ast::{Block, Inline, Map, MetaValue};
+
+use crate::dom::{el, Dom, DomElement};
+
+/// Flattens a metadata value into plain text, e.g. the inlines of `title: Devy *in action*`.
+pub fn meta_text(value: &MetaValue) -> Option<String> {
+    match value {
+        MetaValue::MetaString(s) => Some(s.clone()),
+        MetaValue::MetaBool(b) => Some(b.to_string()),
+        MetaValue::MetaInlines(inlines) => Some(inlines_text(inlines)),
+        MetaValue::MetaBlocks(blocks) => Some(
+            blocks
+                .iter()
+                .filter_map(|block| match block {
+                    Block::Plain(inlines) | Block::Para(inlines) => Some(inlines_text(inlines)),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ),
+        MetaValue::MetaList(_) | MetaValue::MetaMap(_) => None,
+    }
+}
+
+/// The text of each element of a list value, or of the value itself when it is a single item.
+pub fn meta_list(value: &MetaValue) -> Vec<String> {
+    match value {
+        MetaValue::MetaList(values) => values.iter().filter_map(meta_text).collect(),
+        value => meta_text(value).into_iter().collect(),
+    }
+}
+
+fn inlines_text(inlines: &[Inline]) -> String {
+    inlines.iter().map(inline_text).collect()
+}
+
+fn inline_text(inline: &Inline) -> String {
+    match inline {
+        Inline::Str(s) | Inline::Code(_, s) | Inline::Math(_, s) => s.clone(),
+        Inline::Space | Inline::SoftBreak | Inline::LineBreak => " ".to_string(),
+        Inline::Emph(inlines)
+        | Inline::Underline(inlines)
+        | Inline::Strong(inlines)
+        | Inline::Strikeout(inlines)
+        | Inline::Superscript(inlines)
+        | Inline::Subscript(inlines)
+        | Inline::SmallCaps(inlines)
+        | Inline::Quoted(_, inlines)
+        | Inline::Cite(_, inlines)
+        | Inline::Link(_, inlines, _)
+        | Inline::Image(_, inlines, _)
+        | Inline::Span(_, inlines) => inlines_text(inlines),
+        Inline::RawInline(_, _) | Inline::Note(_) => String::new(),
+    }
+}
+
+fn meta_tag(key: &str, name: &str, content: &str) -> DomElement {
+    el("meta").attr(key, name).attr("content", content)
+}
+
+/// The plain text title of an article, from the `title` field of its front matter.
+pub fn title(meta: &Map<String, MetaValue>) -> Option<String> {
+    meta.get("title").and_then(meta_text)
+}
+
+/// The description and Open Graph tags described by an article's front matter.
+///
+/// Reads the `title`, `description`, `date`, `tags` and `image` fields, skipping missing ones.
+pub fn head_tags(meta: &Map<String, MetaValue>) -> Dom {
+    let field = |key: &str| meta.get(key).and_then(meta_text);
+    let mut dom = vec![];
+
+    if let Some(title) = field("title") {
+        dom.push(meta_tag("property", "og:title", &title));
+    }
+
+    if let Some(description) = field("description") {
+        dom.push(meta_tag("name", "description", &description));
+        dom.push(meta_tag("property", "og:description", &description));
+    }
+
+    if !dom.is_empty() {
+        dom.push(meta_tag("property", "og:type", "article"));
+    }
+
+    if let Some(image) = field("image") {
+        dom.push(meta_tag("property", "og:image", &image));
+    }
+
+    if let Some(date) = field("date") {
+        dom.push(meta_tag("property", "article:published_time", &date));
+    }
+
+    let tags = meta.get("tags").map(meta_list).unwrap_or_default();
+    if !tags.is_empty() {
+        dom.push(meta_tag("name", "keywords", &tags.join(", ")));
+    }
+    for tag in &tags {
+        dom.push(meta_tag("property", "article:tag", tag));
+    }
+
+    Dom(dom)
+}
@@ -0,0 +1,157 @@
+use std::collections::BTreeMap;
+
+use pandoc_ast::{Map, MetaValue};
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+
+use crate::dom::HtmlFormat;
+use crate::error::MetaError;
+use crate::meta::{meta_list, meta_text};
+use crate::theme::Theme;
+
+/// Settings that change how the code blocks of an article are rendered.
+///
+/// Each setting can come from several places, later ones taking precedence: the defaults below, the `[render]`
+/// table of `devy.toml`, the `devy` map of the article's front matter, and command-line flags.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Options {
+    /// Frame interval of animated mermaid diagrams without a `rate` attribute, in milliseconds.
+    pub mermaid_rate: f64,
+    /// Script URL mermaid diagrams are rendered with.
+    pub mermaid_cdn: String,
+    /// Show line numbers on every code listing, not just `.linenumbers` ones.
+    pub line_numbers: bool,
+    /// Add a copy button to every code listing, not just `.copy` ones.
+    pub copy: bool,
+    /// Built-in stylesheet of the generated components.
+    pub theme: Theme,
+    /// URLs of stylesheets linked after the theme, to override it.
+    pub stylesheets: Vec<String>,
+    /// Layout of the generated HTML. It is a setting of the build, not of articles, so front matter can't change it.
+    pub html_format: HtmlFormat,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            mermaid_rate: 1000.0,
+            mermaid_cdn: "https://cdn.jsdelivr.net/npm/mermaid@8/dist/mermaid.min.js".to_string(),
+            line_numbers: false,
+            copy: false,
+            theme: Theme::default(),
+            stylesheets: vec![],
+            html_format: HtmlFormat::default(),
+        }
+    }
+}
+
+/// The options set by a single source, such as a configuration file, unset ones being left as they are.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct OptionsLayer {
+    pub mermaid_rate: Option<f64>,
+    pub mermaid_cdn: Option<String>,
+    pub line_numbers: Option<bool>,
+    pub copy: Option<bool>,
+    pub theme: Option<Theme>,
+    pub stylesheets: Option<Vec<String>>,
+    pub html_format: Option<HtmlFormat>,
+    /// Keys that are not options, kept so that they can be reported.
+    #[serde(flatten)]
+    pub unknown: BTreeMap<String, IgnoredAny>,
+}
+
+impl Options {
+    /// Returns these options with every setting of `layer` applied on top.
+    pub fn with(&self, layer: &OptionsLayer) -> Options {
+        Options {
+            mermaid_rate: layer.mermaid_rate.unwrap_or(self.mermaid_rate),
+            mermaid_cdn: layer
+                .mermaid_cdn
+                .clone()
+                .unwrap_or_else(|| self.mermaid_cdn.clone()),
+            line_numbers: layer.line_numbers.unwrap_or(self.line_numbers),
+            copy: layer.copy.unwrap_or(self.copy),
+            theme: layer.theme.unwrap_or(self.theme),
+            stylesheets: layer
+                .stylesheets
+                .clone()
+                .unwrap_or_else(|| self.stylesheets.clone()),
+            html_format: layer.html_format.unwrap_or(self.html_format),
+        }
+    }
+
+    /// Applies the `devy` map of an article's front matter on top of these options, see
+    /// [`OptionsLayer::from_meta`].
+    pub fn with_meta(&self, meta: &Map<String, MetaValue>) -> Result<Options, MetaError> {
+        Ok(self.with(&OptionsLayer::from_meta(meta)?))
+    }
+}
+
+impl OptionsLayer {
+    /// Reads the options set by the `devy` map of an article's front matter:
+    ///
+    /// ```yaml
+    /// devy:
+    ///   mermaid-rate: 500
+    ///   line-numbers: true
+    ///   copy: true
+    ///   theme: dark
+    ///   stylesheets: [/css/article.css]
+    /// ```
+    pub fn from_meta(meta: &Map<String, MetaValue>) -> Result<OptionsLayer, MetaError> {
+        let mut layer = OptionsLayer::default();
+
+        let Some(devy) = meta.get("devy") else {
+            return Ok(layer);
+        };
+        let MetaValue::MetaMap(devy) = devy else {
+            return Err(MetaError::new("devy", "expected a map of devy options"));
+        };
+
+        for (key, value) in devy {
+            let path = format!("devy.{}", key);
+            match key.as_str() {
+                "mermaid-rate" => layer.mermaid_rate = Some(meta_number(&path, value)?),
+                "mermaid-cdn" => layer.mermaid_cdn = Some(meta_string(&path, value)?),
+                "line-numbers" => layer.line_numbers = Some(meta_bool(&path, value)?),
+                "copy" => layer.copy = Some(meta_bool(&path, value)?),
+                "theme" => {
+                    let theme = meta_string(&path, value)?;
+                    layer.theme = Some(
+                        theme
+                            .parse()
+                            .map_err(|e: String| MetaError::new(&path, e))?,
+                    );
+                }
+                "stylesheets" => layer.stylesheets = Some(meta_list(value)),
+                _ => {
+                    return Err(MetaError::new(
+                        path,
+                        format!("unknown devy option `{}`", key),
+                    ))
+                }
+            }
+        }
+
+        Ok(layer)
+    }
+}
+
+fn meta_string(key: &str, value: &MetaValue) -> Result<String, MetaError> {
+    meta_text(value).ok_or_else(|| MetaError::new(key, "expected a string"))
+}
+
+fn meta_number(key: &str, value: &MetaValue) -> Result<f64, MetaError> {
+    meta_text(value)
+        .and_then(|text| text.trim().parse().ok())
+        .ok_or_else(|| MetaError::new(key, "expected a number"))
+}
+
+fn meta_bool(key: &str, value: &MetaValue) -> Result<bool, MetaError> {
+    match meta_text(value).as_deref().map(str::trim) {
+        Some("true") => Ok(true),
+        Some("false") => Ok(false),
+        _ => Err(MetaError::new(key, "expected `true` or `false`")),
+    }
+}
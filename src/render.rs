@@ -0,0 +1,505 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use pandoc::Pandoc;
+use pandoc_ast::{Attr, Block, Format, MetaValue};
+
+use crate::dcl::{parse_dcl, Definition, RUNTIME};
+use crate::dom::{el, parse_leaf, text, Dom, DomElement, HtmlFormat};
+use crate::error::{BlockError, BlockErrorKind, Error};
+use crate::meta::{head_tags, title as meta_title};
+use crate::options::{Options, OptionsLayer};
+use crate::template::Page;
+
+/// A button copying the code stored in the hidden input `id`, and the script listening to its clicks. The
+/// listener is not an `onclick` attribute, which a strict Content-Security-Policy would block.
+fn copy_button(id: &str) -> (DomElement, String) {
+    let button = el("button")
+        .class("devy-copy")
+        .id(format!("{}-copy", id))
+        .text("Copy");
+    let script = format!(
+        r#"
+document.getElementById("{}-copy").addEventListener("click", () => {{
+    navigator.clipboard.writeText(document.getElementById("{}").value);
+}});
+"#,
+        id, id
+    );
+    (button, script)
+}
+
+/// The HTML a code block is rendered to.
+#[derive(Clone, Debug, Default)]
+pub struct RenderedBlock {
+    /// Elements replacing the code block.
+    pub dom: Dom,
+    /// Javascript to run once the page is loaded, such as the event listeners of DCL components.
+    pub script: String,
+    /// Whether the script uses the store of DCL values, which is then defined once for the whole article.
+    pub store: bool,
+}
+
+/// Renders a single code block, given its pandoc attributes and source, into HTML elements. `article` holds the
+/// variables defined by the other DCL blocks of the article, which the functions of a DCL block may depend on.
+pub fn render_code_block(
+    (identifier, kinds, kvs): &Attr,
+    code: &str,
+    options: &Options,
+    article: &[Definition],
+) -> Result<RenderedBlock, BlockErrorKind> {
+    let mut dom = vec![];
+    let mut script = String::new();
+    let mut store = false;
+    // Create a hidden input variable to store the code as its value
+    dom.push(
+        el("input")
+            .attr("type", "hidden")
+            .id(identifier)
+            .attr("value", code),
+    );
+
+    let language = if let Some(s) = kinds.first() { s } else { "" };
+
+    let has = |kind: &str| kinds.iter().any(|k| k == kind);
+    // Listings are code shown to the reader, as opposed to DCL, diagrams and hidden scripts
+    let is_listing = !["dcl", "mermaid"].contains(&language) && (!has("script") || has("show"));
+
+    // Copy buttons find the code by the identifier of the block
+    let copy = has("copy") || (options.copy && is_listing && !has("nocopy"));
+    if copy && !identifier.is_empty() {
+        let (button, listener) = copy_button(identifier);
+        dom.push(button);
+        script.push_str(&listener);
+    }
+
+    match language {
+        "dcl" => {
+            let (mut components, dcl_script) = parse_dcl(code)?.render_in(article)?;
+            dom.append(&mut components.0);
+            script.push_str(&dcl_script);
+            store = true;
+        }
+        "mermaid" => {
+            // <script src="https://cdn.jsdelivr.net/npm/mermaid@8/dist/mermaid.min.js"></script>
+
+            let frame_rate = if let Some((_, value)) = kvs.iter().find(|(k, _)| k == "rate") {
+                let rate = value
+                    .parse::<f64>()
+                    .map_err(|_| BlockErrorKind::Attribute {
+                        key: "rate".to_string(),
+                        value: value.clone(),
+                        message: format!(
+                            "invalid mermaid frame rate `{}`, expected a number of milliseconds",
+                            value
+                        ),
+                    })?;
+                Some(rate)
+            } else if kinds.contains(&"animate".to_string()) {
+                Some(options.mermaid_rate)
+            } else {
+                None
+            };
+
+            dom.push(el("script").attr("src", &options.mermaid_cdn));
+
+            // <script>
+            //     setInterval(() => {
+            //         let value = document.getElementById("diagram").value;
+            //         let frames = value.split("\n");
+            //         let numberOfFrames = frames.length - 1;
+            //         let currentSecond = Math.floor(Date.now() / 1000);
+            //         let currentFrame = (currentSecond % numberOfFrames) + 1;
+            //         let frameContent = frames.slice(0, currentFrame + 1).join("\n");
+            //         mermaid.render(
+            //             "diagram-renderer",
+            //             frameContent,
+            //             (code) => {document.getElementById("diagram-rendered").innerHTML = code}
+            //         )
+            //     }, 1000);
+            // </script>
+
+            if let Some(frame_rate) = frame_rate {
+                script.push_str(&format!(
+                    r#"
+                    setInterval(() => {{
+                        let value = document.getElementById("{}").value;
+                        let frames = value.split("\n");
+                        let numberOfFrames = frames.length - 1;
+                        let currentSecond = Math.floor(Date.now() / {});
+                        let currentFrame = (currentSecond % numberOfFrames) + 1;
+                        let frameContent = frames.slice(0, currentFrame + 1).join("\n");
+                        mermaid.render(
+                            "{}-renderer",
+                            frameContent,
+                            (code) => {{document.getElementById("{}-rendered").innerHTML = code}}
+                        )
+                    }}, {});
+                    "#,
+                    identifier, frame_rate, identifier, identifier, frame_rate
+                ));
+            } else {
+                script.push_str(&format!(
+                    r#"
+                        setTimeout(() => {{
+                        mermaid.render(
+                            "{}-renderer",
+                            document.getElementById("{}").value,
+                            (code) => {{document.getElementById("{}-rendered").innerHTML = code}}
+                        ), 100}});
+                        "#,
+                    identifier, identifier, identifier
+                ));
+            }
+
+            // <div id="diagram-rendered"></div>
+
+            dom.push(
+                el("div")
+                    .id(format!("{}-rendered", identifier))
+                    .class("devy-mermaid"),
+            );
+        }
+        _ => {
+            if kinds.contains(&"script".to_string()) && ["js", "javascript"].contains(&language) {
+                dom.push(DomElement::script(code));
+            };
+
+            if is_listing {
+                let code = code.trim();
+                let pre = el("pre").class("devy-code").child(
+                    el("code")
+                        .class(&format!("language-{}", language))
+                        .attr("name", identifier)
+                        .text(code),
+                );
+
+                if has("linenumbers") || (options.line_numbers && !has("nolinenumbers")) {
+                    let number_of_lines = code.lines().count();
+                    // Create a vertical list of numbers
+                    let mut numbers = vec![];
+                    for n in 1..=number_of_lines {
+                        if n > 1 {
+                            numbers.push(text("\n"));
+                        }
+                        numbers.push(el("span").text(n.to_string()));
+                    }
+                    // `line-numbers` is kept for stylesheets written before the `devy-` classes
+                    let numbers = el("pre")
+                        .class("line-numbers")
+                        .class("devy-line-numbers")
+                        .child(el("code").children(numbers));
+
+                    dom.push(el("div").class("devy-listing").child(numbers).child(pre));
+                } else {
+                    dom.push(pre);
+                }
+            }
+        }
+    }
+
+    Ok(RenderedBlock {
+        dom: Dom(dom),
+        script,
+        store,
+    })
+}
+
+/// Renders DCL, mermaid and script code blocks of a document into raw HTML, stopping at the first failing block.
+pub fn render_code_blocks(
+    mut pandoc: pandoc_ast::Pandoc,
+    options: &Options,
+) -> Result<pandoc_ast::Pandoc, BlockError> {
+    let definitions = dcl_definitions(&pandoc.blocks);
+    let mut seen: Vec<String> = vec![];
+    let mut blocks = vec![];
+    let mut scripts = vec![];
+    let mut store = false;
+    for block in pandoc.blocks {
+        match block {
+            Block::CodeBlock(attr, code) => {
+                let rendered = render_block(&attr, &code, options, &definitions, &seen)?;
+                seen.push(code);
+                blocks.extend(raw_blocks(rendered.dom, options.html_format));
+                scripts.push(rendered.script);
+                store |= rendered.store;
+            }
+            block => blocks.push(block),
+        }
+    }
+    blocks.extend(runtime_block(&scripts, store, options.html_format));
+    pandoc.blocks = blocks;
+    Ok(pandoc)
+}
+
+/// Like [`render_code_blocks`], but replaces failing blocks with an error panel and returns every error.
+pub fn render_code_blocks_lenient(
+    mut pandoc: pandoc_ast::Pandoc,
+    options: &Options,
+) -> (pandoc_ast::Pandoc, Vec<BlockError>) {
+    let definitions = dcl_definitions(&pandoc.blocks);
+    let mut seen: Vec<String> = vec![];
+    let mut errors = vec![];
+    let mut blocks = vec![];
+    let mut scripts = vec![];
+    let mut store = false;
+    for block in pandoc.blocks {
+        match block {
+            Block::CodeBlock(attr, code) => {
+                let rendered = render_block(&attr, &code, options, &definitions, &seen)
+                    .unwrap_or_else(|error| {
+                        let panel = Dom(vec![error_panel(&error)]);
+                        errors.push(error);
+                        RenderedBlock {
+                            dom: panel,
+                            ..RenderedBlock::default()
+                        }
+                    });
+                seen.push(code);
+                blocks.extend(raw_blocks(rendered.dom, options.html_format));
+                scripts.push(rendered.script);
+                store |= rendered.store;
+            }
+            block => blocks.push(block),
+        }
+    }
+    blocks.extend(runtime_block(&scripts, store, options.html_format));
+    pandoc.blocks = blocks;
+    (pandoc, errors)
+}
+
+/// One raw HTML block per top-level element, so that scripts and styles can be moved into a [`Page`] on their
+/// own.
+fn raw_blocks(dom: Dom, format: HtmlFormat) -> impl Iterator<Item = Block> {
+    dom.0.into_iter().map(move |element| {
+        Block::RawBlock(
+            Format("HTML".to_string()),
+            Dom(vec![element]).to_html(format),
+        )
+    })
+}
+
+/// A single script running the scripts of every code block once the page is loaded, after defining the store of DCL
+/// values they share if `store` is set. Each block runs in its own `try`, so that a failing block doesn't stop the
+/// others.
+fn runtime_block(scripts: &[String], store: bool, format: HtmlFormat) -> Option<Block> {
+    let mut body: Vec<String> = scripts
+        .iter()
+        .filter(|script| !script.trim().is_empty())
+        .map(|script| {
+            format!(
+                "try {{\n{}\n}} catch (error) {{\n    console.error(error);\n}}",
+                script.trim_matches('\n').trim_end()
+            )
+        })
+        .collect();
+    if body.is_empty() {
+        return None;
+    }
+    if store {
+        body.insert(0, RUNTIME.trim_end().to_string());
+    }
+    let script = DomElement::deferred_script(&body.join("\n"));
+    raw_blocks(Dom(vec![script]), format).next()
+}
+
+/// The variables defined by each code block of a document, none for blocks other than DCL and for blocks that fail
+/// to parse.
+fn dcl_definitions(blocks: &[Block]) -> Vec<Vec<Definition>> {
+    blocks
+        .iter()
+        .filter_map(|block| match block {
+            Block::CodeBlock((_, kinds, _), code) if kinds.first().is_some_and(|k| k == "dcl") => {
+                Some(parse_dcl(code).map_or(vec![], |dcl| dcl.definitions()))
+            }
+            Block::CodeBlock(..) => Some(vec![]),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Renders the code block following the `seen` ones, naming it after its position if it has no identifier so that
+/// the elements generated for it get unique ids. `definitions` are the DCL variables of every code block.
+fn render_block(
+    attr: &Attr,
+    code: &str,
+    options: &Options,
+    definitions: &[Vec<Definition>],
+    seen: &[String],
+) -> Result<RenderedBlock, BlockError> {
+    let mut attr = attr.clone();
+    if attr.0.is_empty() {
+        attr.0 = format!("devy-block-{}", seen.len() + 1);
+    }
+    let article: Vec<Definition> = definitions
+        .iter()
+        .enumerate()
+        .filter(|&(index, _)| index != seen.len())
+        .flat_map(|(_, definitions)| definitions.iter().cloned())
+        .collect();
+    render_code_block(&attr, code, options, &article).map_err(|error| BlockError {
+        code: code.to_string(),
+        occurrence: seen.iter().filter(|c| *c == code).count(),
+        error,
+    })
+}
+
+/// A visible replacement for a code block that failed to render, showing the error and the block's source.
+fn error_panel(error: &BlockError) -> DomElement {
+    el("div")
+        .class("devy-error")
+        .child(el("strong").text(format!("devy: {}", error.error)))
+        .child(el("pre").text(&error.code))
+}
+
+/// Adds the description and Open Graph tags described by the front matter to the `header-includes` metadata,
+/// which pandoc's standalone templates place in `<head>`.
+pub fn insert_head_tags(mut pandoc: pandoc_ast::Pandoc) -> pandoc_ast::Pandoc {
+    let tags = head_tags(&pandoc.meta);
+    if tags.0.is_empty() {
+        return pandoc;
+    }
+
+    let include = MetaValue::MetaBlocks(vec![Block::RawBlock(
+        Format("HTML".to_string()),
+        tags.to_raw_html(),
+    )]);
+    let includes = match pandoc.meta.remove("header-includes") {
+        Some(MetaValue::MetaList(mut includes)) => {
+            includes.push(include);
+            includes
+        }
+        Some(existing) => vec![existing, include],
+        None => vec![include],
+    };
+    pandoc
+        .meta
+        .insert("header-includes".to_string(), MetaValue::MetaList(includes));
+    pandoc
+}
+
+/// Fills in the title of `page` from the front matter, and its head tags unless `head_tags` is false, then moves
+/// every top-level `<script>`, `<style>` and stylesheet `<link>` block of the document into it. Repeated scripts,
+/// such as the mermaid library loaded by every diagram, are only kept once.
+pub fn collect_page(
+    mut pandoc: pandoc_ast::Pandoc,
+    page: &mut Page,
+    head_tags: bool,
+) -> pandoc_ast::Pandoc {
+    if let Some(title) = meta_title(&pandoc.meta) {
+        page.title = title;
+    }
+    if head_tags {
+        page.head.0.extend(crate::meta::head_tags(&pandoc.meta).0);
+    }
+
+    let mut collected: Vec<String> = vec![];
+    pandoc.blocks.retain(|block| {
+        let Block::RawBlock(Format(format), html) = block else {
+            return true;
+        };
+        let html = html.trim();
+        if !format.eq_ignore_ascii_case("html") {
+            return true;
+        }
+        let Some(element) = parse_leaf(html) else {
+            return true;
+        };
+        let slot = match &element {
+            DomElement::Element { tag, .. } if tag == "script" => &mut page.scripts,
+            DomElement::Element { tag, .. } if tag == "style" => &mut page.styles,
+            DomElement::Element {
+                tag, attributes, ..
+            } if tag == "link"
+                && attributes
+                    .iter()
+                    .any(|(k, v)| k == "rel" && v.eq_ignore_ascii_case("stylesheet")) =>
+            {
+                &mut page.styles
+            }
+            _ => return true,
+        };
+        if !collected.iter().any(|c| c == html) {
+            collected.push(html.to_string());
+            slot.0.push(element);
+        }
+        false
+    });
+
+    pandoc
+}
+
+/// Inserts the stylesheet of the theme, and links to the other stylesheets of `options`, at the start of the
+/// document.
+pub fn insert_styles(mut pandoc: pandoc_ast::Pandoc, options: &Options) -> pandoc_ast::Pandoc {
+    let mut styles = vec![];
+    if let Some(css) = options.theme.css() {
+        styles.push(el("style").text(css));
+    }
+    for href in &options.stylesheets {
+        styles.push(el("link").attr("rel", "stylesheet").attr("href", href));
+    }
+
+    pandoc
+        .blocks
+        .splice(0..0, raw_blocks(Dom(styles), options.html_format));
+    pandoc
+}
+
+/// Applies every devy transformation to a document, with `options` updated by its front matter.
+pub fn render(pandoc: pandoc_ast::Pandoc, options: &Options) -> Result<pandoc_ast::Pandoc, Error> {
+    let options = options.with_meta(&pandoc.meta)?;
+    let pandoc = render_code_blocks(pandoc, &options)?;
+    Ok(insert_styles(insert_head_tags(pandoc), &options))
+}
+
+/// Registers [`render_code_blocks`] as a filter of a pandoc invocation, or [`render_code_blocks_lenient`] when
+/// `keep_going` is set. The article's front matter is applied on top of `options`, and `overrides` on top of that.
+///
+/// Errors are pushed to `errors`, as filters cannot fail. Without `keep_going` a failing document is passed
+/// through unchanged.
+pub fn code_block_filter(
+    pandoc: &mut Pandoc,
+    errors: Rc<RefCell<Vec<Error>>>,
+    options: Options,
+    overrides: OptionsLayer,
+    keep_going: bool,
+) {
+    pandoc.add_filter(move |json| {
+        let document = pandoc_ast::Pandoc::from_json(&json);
+        let options = match options.with_meta(&document.meta) {
+            Ok(options) => options.with(&overrides),
+            Err(error) => {
+                errors.borrow_mut().push(error.into());
+                if !keep_going {
+                    return json;
+                }
+                options.with(&overrides)
+            }
+        };
+
+        if keep_going {
+            let (document, found) = render_code_blocks_lenient(document, &options);
+            errors
+                .borrow_mut()
+                .extend(found.into_iter().map(Error::from));
+            return insert_styles(document, &options).to_json();
+        }
+        match render_code_blocks(document, &options) {
+            Ok(document) => insert_styles(document, &options).to_json(),
+            Err(error) => {
+                errors.borrow_mut().push(error.into());
+                json
+            }
+        }
+    });
+}
+
+/// Registers [`collect_page`] as a filter of a pandoc invocation, filling in `page` as the document goes through.
+pub fn page_filter(pandoc: &mut Pandoc, page: Rc<RefCell<Page>>, head_tags: bool) {
+    pandoc.add_filter(move |json| {
+        pandoc_ast::filter(json, |document| {
+            collect_page(document, &mut page.borrow_mut(), head_tags)
+        })
+    });
+}
@@ -0,0 +1,88 @@
+const devy = (function () {
+    // The current value of every DCL variable of the article, and the functions subscribed to each of them
+    const values = {};
+    const subscribers = {};
+    // Derived variables, each block's after the variables of the block it depends on, and when each variable last
+    // changed, counted in updates
+    const derivations = [];
+    const changed = {};
+    let clock = 0;
+
+    const notify = function (name) {
+        (subscribers[name] || []).forEach(function (subscriber) {
+            subscriber(values[name]);
+        });
+    };
+
+    // Computes a derived value, reporting a failing function rather than stopping every update
+    const evaluate = function (compute) {
+        try {
+            return compute();
+        } catch (error) {
+            console.error(error);
+            return undefined;
+        }
+    };
+
+    const assign = function (name, value) {
+        values[name] = value;
+        changed[name] = ++clock;
+        notify(name);
+    };
+
+    // Sets several variables at once, then recomputes the variables derived from any of them. Derivations depending
+    // on later blocks can be out of order, so they are passed over until none is stale, at most once per derivation.
+    const update = function (changes) {
+        Object.keys(changes).forEach(function (name) {
+            assign(name, changes[name]);
+        });
+        let stale = true;
+        for (let pass = 0; stale && pass <= derivations.length; pass++) {
+            stale = false;
+            derivations.forEach(function (derivation) {
+                const outdated = derivation.dependencies.some(function (d) {
+                    return (changed[d] || 0) > derivation.computed;
+                });
+                if (outdated) {
+                    assign(derivation.name, evaluate(derivation.compute));
+                    derivation.computed = clock;
+                    stale = true;
+                }
+            });
+        }
+    };
+
+    return {
+        get: function (name) {
+            return values[name];
+        },
+        // Declaring a variable also recomputes the variables of earlier blocks derived from it
+        declare: function (name, value) {
+            const changes = {};
+            changes[name] = value;
+            update(changes);
+        },
+        derive: function (name, dependencies, compute) {
+            const value = evaluate(compute);
+            derivations.push({ name: name, dependencies: dependencies, compute: compute, computed: clock });
+            this.declare(name, value);
+        },
+        subscribe: function (name, subscriber) {
+            (subscribers[name] = subscribers[name] || []).push(subscriber);
+            subscriber(values[name]);
+        },
+        set: function (name, value) {
+            const changes = {};
+            changes[name] = value;
+            update(changes);
+        },
+        // Runs the action of a button, applying the changes it returns and counting the click
+        click: function (name, action) {
+            const changes = action();
+            if (changes && typeof changes === "object" && !Array.isArray(changes)) {
+                update(changes);
+            }
+            this.set(name, values[name] + 1);
+        },
+    };
+})();
@@ -0,0 +1,207 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::cli::ServeArgs;
+use crate::watch::watch_with;
+
+/// Path the live-reload client polls for the current build number.
+const VERSION_PATH: &str = "/__devy/version";
+
+/// Injected into every served HTML page, reloads the page once the build number changes.
+const LIVE_RELOAD_CLIENT: &str = r#"<script>
+(() => {
+    let version = null;
+    setInterval(async () => {
+        try {
+            const current = await (await fetch("/__devy/version")).text();
+            if (version !== null && current !== version) {
+                location.reload();
+            }
+            version = current;
+        } catch (e) {}
+    }, 500);
+})();
+</script>"#;
+
+/// Serves the directory of the built article on localhost while rebuilding it like `devy watch`.
+pub fn serve(args: &ServeArgs, verbose: u8) -> Result<(), Box<dyn std::error::Error>> {
+    let output = args
+        .watch
+        .build
+        .output_path()
+        .ok_or("`devy serve` needs an output file, it cannot serve stdout")?;
+    let root = match output.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let index = PathBuf::from(output.file_name().ok_or("output path has no file name")?);
+
+    let listener = TcpListener::bind((args.host.as_str(), args.port))?;
+    eprintln!(
+        "serving {} at http://{}:{}/",
+        output.display(),
+        args.host,
+        args.port
+    );
+
+    let version = Arc::new(AtomicU64::new(0));
+    let server_version = version.clone();
+    std::thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let (root, index, version) = (root.clone(), index.clone(), server_version.clone());
+            std::thread::spawn(move || {
+                if let Err(e) = respond(stream, &root, &index, &version) {
+                    if verbose > 0 {
+                        eprintln!("error serving request: {}", e);
+                    }
+                }
+            });
+        }
+    });
+
+    watch_with(&args.watch, verbose, || {
+        version.fetch_add(1, Ordering::SeqCst);
+    })
+}
+
+fn respond(
+    mut stream: TcpStream,
+    root: &Path,
+    index: &Path,
+    version: &AtomicU64,
+) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // Skip the headers, nothing in them changes the response
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (
+        parts.next().unwrap_or_default(),
+        parts.next().unwrap_or("/"),
+    );
+    let path = target.split(['?', '#']).next().unwrap_or("/");
+
+    if method != "GET" {
+        return write_response(
+            &mut stream,
+            "405 Method Not Allowed",
+            "text/plain",
+            b"method not allowed",
+        );
+    }
+    if path == VERSION_PATH {
+        let body = version.load(Ordering::SeqCst).to_string();
+        return write_response(&mut stream, "200 OK", "text/plain", body.as_bytes());
+    }
+
+    let Some(file) = resolve(root, index, path) else {
+        return write_response(&mut stream, "404 Not Found", "text/plain", b"not found");
+    };
+    let Ok(mut body) = std::fs::read(&file) else {
+        return write_response(&mut stream, "404 Not Found", "text/plain", b"not found");
+    };
+
+    let content_type = content_type(&file);
+    if content_type.starts_with("text/html") {
+        body = inject_live_reload(&String::from_utf8_lossy(&body)).into_bytes();
+    }
+    write_response(&mut stream, "200 OK", content_type, &body)
+}
+
+/// Maps a request path to a file under `root`, refusing paths that would escape it.
+fn resolve(root: &Path, index: &Path, path: &str) -> Option<PathBuf> {
+    let relative = PathBuf::from(percent_decode(path.trim_start_matches('/')));
+    if relative
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return None;
+    }
+
+    let file = root.join(relative);
+    if file.is_dir() {
+        let nested = file.join("index.html");
+        return Some(if nested.is_file() {
+            nested
+        } else {
+            root.join(index)
+        });
+    }
+    Some(file)
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = (bytes[i] == b'%')
+            .then(|| s.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match hex {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+fn inject_live_reload(html: &str) -> String {
+    match html.rfind("</body>") {
+        Some(i) => format!("{}{}\n{}", &html[..i], LIVE_RELOAD_CLIENT, &html[i..]),
+        None => format!("{}\n{}", html, LIVE_RELOAD_CLIENT),
+    }
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or_default()
+    {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" | "mjs" => "text/javascript; charset=utf-8",
+        "json" => "application/json",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "ico" => "image/x-icon",
+        "wasm" => "application/wasm",
+        "txt" | "md" => "text/plain; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+fn write_response(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> std::io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()
+}
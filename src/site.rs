@@ -0,0 +1,126 @@
+use std::path::{Path, PathBuf};
+
+use crate::build::build;
+use crate::cli::{BuildArgs, SiteArgs};
+
+/// Renders every markdown file under the content directory to HTML at the same relative path in the output
+/// directory, and copies every other file as a static asset. Files whose output is newer than their source, and for
+/// articles than the page template and configuration file, are skipped unless `--force` is given.
+pub fn site(args: &SiteArgs, verbose: u8) -> Result<(), Box<dyn std::error::Error>> {
+    let content = args
+        .content
+        .as_deref()
+        .ok_or("no content directory, pass one or set `site.content-dir` in devy.toml")?;
+    if !content.is_dir() {
+        return Err(format!("content directory {} does not exist", content.display()).into());
+    }
+    let output = args.output.as_deref().unwrap_or(Path::new("public"));
+
+    // Articles are also rebuilt when the page template or the configuration changed since
+    let dependencies: Vec<&Path> = [args.page.template.as_deref(), args.config.as_deref()]
+        .into_iter()
+        .flatten()
+        .collect();
+
+    let mut files = vec![];
+    collect_files(content, output, &mut files)?;
+
+    let (mut built, mut copied, mut skipped, mut failed) = (0, 0, 0, 0);
+
+    for source in files {
+        let relative = source.strip_prefix(content)?;
+        let is_article = matches!(
+            source.extension().and_then(|e| e.to_str()),
+            Some("md" | "markdown")
+        );
+        let target = if is_article {
+            output.join(relative).with_extension("html")
+        } else {
+            output.join(relative)
+        };
+
+        let sources = if is_article {
+            [&[source.as_path()], dependencies.as_slice()].concat()
+        } else {
+            vec![source.as_path()]
+        };
+        if !args.force && is_up_to_date(&sources, &target) {
+            skipped += 1;
+            continue;
+        }
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        if is_article {
+            let build_args = BuildArgs {
+                inputs: vec![source.clone()],
+                output: Some(target),
+                output_dir: None,
+                format: "html".to_string(),
+                page: args.page.clone(),
+                filters: args.filters.clone(),
+            };
+            match build(&build_args, verbose) {
+                Ok(()) => built += 1,
+                Err(e) => {
+                    eprintln!("error: {}: {}", source.display(), e);
+                    failed += 1;
+                }
+            }
+        } else {
+            if verbose > 0 {
+                eprintln!("copying {} to {}", source.display(), target.display());
+            }
+            std::fs::copy(&source, &target)?;
+            copied += 1;
+        }
+    }
+
+    eprintln!(
+        "built {} article(s), copied {} asset(s), skipped {} unchanged file(s)",
+        built, copied, skipped
+    );
+
+    if failed > 0 {
+        return Err(format!("{} article(s) failed to build", failed).into());
+    }
+    Ok(())
+}
+
+/// Recursively lists the files of `dir`, leaving out hidden files and the output directory.
+fn collect_files(dir: &Path, output: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    let mut entries = std::fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+    entries.sort_by_key(|entry| entry.path());
+
+    for entry in entries {
+        let path = entry.path();
+        if entry.file_name().to_string_lossy().starts_with('.') || is_same_path(&path, output) {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            collect_files(&path, output, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn is_same_path(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+/// Whether `target` is newer than every one of the `sources` it is built from.
+fn is_up_to_date(sources: &[&Path], target: &Path) -> bool {
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    let Some(target) = modified(target) else {
+        return false;
+    };
+    sources
+        .iter()
+        .all(|source| modified(source).is_some_and(|source| target >= source))
+}
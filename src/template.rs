@@ -0,0 +1,109 @@
+use crate::dom::{el, escape_text, Dom, HtmlFormat};
+use crate::error::TemplateError;
+
+/// The template pages are rendered with when no other one is given.
+pub const DEFAULT_TEMPLATE: &str = include_str!("templates/default.html");
+
+/// The parts of an article a template places into a full HTML document.
+#[derive(Clone, Debug, Default)]
+pub struct Page {
+    pub title: String,
+    /// Elements for `<head>`, such as the charset and Open Graph tags.
+    pub head: Dom,
+    /// `<style>` and stylesheet `<link>` elements.
+    pub styles: Dom,
+    /// The rendered article.
+    pub body: String,
+    /// Scripts that run once the article is loaded.
+    pub scripts: Dom,
+}
+
+impl Page {
+    /// Adds a `<meta charset="UTF-8">` tag at the start of the head.
+    pub fn insert_utf8_meta(&mut self) {
+        self.head.0.insert(0, el("meta").attr("charset", "UTF-8"));
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Slot {
+    Title,
+    Head,
+    Styles,
+    Body,
+    Scripts,
+}
+
+#[derive(Clone, Debug)]
+enum Part {
+    Text(String),
+    Slot(Slot),
+}
+
+/// An HTML document with `{{ slot }}` placeholders for the parts of a [`Page`]: `title`, `head`, `styles`, `body`
+/// and `scripts`. Every template needs a `body` slot.
+#[derive(Clone, Debug)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl Default for Template {
+    fn default() -> Self {
+        Template::parse(DEFAULT_TEMPLATE).expect("the default template is valid")
+    }
+}
+
+impl Template {
+    pub fn parse(source: &str) -> Result<Template, TemplateError> {
+        let mut parts = vec![];
+        let mut rest = source;
+
+        while let Some(start) = rest.find("{{") {
+            parts.push(Part::Text(rest[..start].to_string()));
+            let end = rest[start..]
+                .find("}}")
+                .ok_or_else(|| TemplateError::new("unclosed `{{` in template"))?;
+            let slot = match rest[start + 2..start + end].trim() {
+                "title" => Slot::Title,
+                "head" => Slot::Head,
+                "styles" => Slot::Styles,
+                "body" => Slot::Body,
+                "scripts" => Slot::Scripts,
+                other => {
+                    return Err(TemplateError::new(format!(
+                        "unknown template slot `{}`, expected one of title, head, styles, body or scripts",
+                        other
+                    )))
+                }
+            };
+            parts.push(Part::Slot(slot));
+            rest = &rest[start + end + 2..];
+        }
+        parts.push(Part::Text(rest.to_string()));
+
+        if !parts
+            .iter()
+            .any(|part| matches!(part, Part::Slot(Slot::Body)))
+        {
+            return Err(TemplateError::new("template has no `{{ body }}` slot"));
+        }
+
+        Ok(Template { parts })
+    }
+
+    /// Fills the slots of the template with `page`, writing its elements in `format`.
+    pub fn render(&self, page: &Page, format: HtmlFormat) -> String {
+        let mut html = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => html.push_str(text),
+                Part::Slot(Slot::Title) => html.push_str(&escape_text(&page.title)),
+                Part::Slot(Slot::Head) => html.push_str(&page.head.to_html(format)),
+                Part::Slot(Slot::Styles) => html.push_str(&page.styles.to_html(format)),
+                Part::Slot(Slot::Body) => html.push_str(&page.body),
+                Part::Slot(Slot::Scripts) => html.push_str(&page.scripts.to_html(format)),
+            }
+        }
+        html
+    }
+}
@@ -0,0 +1,71 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+const BASE: &str = include_str!("themes/base.css");
+const LIGHT: &str = include_str!("themes/light.css");
+const DARK: &str = include_str!("themes/dark.css");
+
+/// A built-in stylesheet for the components and code listings devy generates.
+///
+/// Every generated element carries a `devy-` class, so a theme can be changed by overriding its `--devy-` CSS
+/// variables or rules in another stylesheet, or replaced altogether with [`Theme::None`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Theme {
+    /// Light colors, switching to dark ones when the reader's system prefers them.
+    #[default]
+    Auto,
+    Light,
+    Dark,
+    /// No stylesheet, leaving the classes to the page's own styles.
+    None,
+}
+
+impl Theme {
+    pub const NAMES: [&'static str; 4] = ["auto", "light", "dark", "none"];
+
+    /// The stylesheet of the theme, `None` for [`Theme::None`].
+    pub fn css(&self) -> Option<String> {
+        match self {
+            Theme::Auto => Some(format!(
+                "{}\n@media (prefers-color-scheme: dark) {{\n{}}}\n\n{}",
+                LIGHT, DARK, BASE
+            )),
+            Theme::Light => Some(format!("{}\n{}", LIGHT, BASE)),
+            Theme::Dark => Some(format!("{}\n{}", DARK, BASE)),
+            Theme::None => None,
+        }
+    }
+}
+
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Theme::Auto),
+            "light" => Ok(Theme::Light),
+            "dark" => Ok(Theme::Dark),
+            "none" => Ok(Theme::None),
+            other => Err(format!(
+                "unknown theme `{}`, expected one of {}",
+                other,
+                Theme::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Theme::Auto => "auto",
+            Theme::Light => "light",
+            Theme::Dark => "dark",
+            Theme::None => "none",
+        };
+        write!(f, "{}", name)
+    }
+}
@@ -0,0 +1,102 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::build::build;
+use crate::cli::WatchArgs;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Builds the article, then rebuilds it every time one of the watched files changes.
+///
+/// Build errors are printed and watching continues, so the loop only ends when the process is interrupted.
+pub fn watch(args: &WatchArgs, verbose: u8) -> Result<(), Box<dyn std::error::Error>> {
+    watch_with(args, verbose, || {})
+}
+
+/// Like [`watch`], calling `on_build` after every successful build.
+pub fn watch_with(
+    args: &WatchArgs,
+    verbose: u8,
+    mut on_build: impl FnMut(),
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut last_seen = None;
+
+    loop {
+        let files = watched_files(args);
+        let stamps: Vec<(PathBuf, Option<SystemTime>)> = files
+            .into_iter()
+            .map(|path| {
+                let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+                (path, modified)
+            })
+            .collect();
+
+        if last_seen.as_ref() != Some(&stamps) {
+            if last_seen.is_some() {
+                eprintln!("change detected, rebuilding");
+            } else if verbose > 0 {
+                let names: Vec<String> = stamps
+                    .iter()
+                    .map(|(p, _)| p.display().to_string())
+                    .collect();
+                eprintln!("watching {}", names.join(", "));
+            }
+
+            match build(&args.build, verbose) {
+                Ok(()) => {
+                    eprintln!("built {}", describe_output(args));
+                    on_build();
+                }
+                Err(e) => eprintln!("error: {}", e),
+            }
+            last_seen = Some(stamps);
+        }
+
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn describe_output(args: &WatchArgs) -> String {
+    args.build
+        .output_path()
+        .map_or("to stdout".to_string(), |p| p.display().to_string())
+}
+
+/// The inputs, the page template, the files passed with `--include`, and local files the inputs load through
+/// `src` attributes.
+fn watched_files(args: &WatchArgs) -> Vec<PathBuf> {
+    let mut files = args.build.inputs.clone();
+    files.extend(args.build.page.template.iter().cloned());
+    files.extend(args.include.iter().cloned());
+
+    for input in &args.build.inputs {
+        let Ok(markdown) = std::fs::read_to_string(input) else {
+            continue;
+        };
+        let base = input.parent().unwrap_or(Path::new(""));
+        for reference in src_references(&markdown) {
+            let path = base.join(reference);
+            if path.is_file() && !files.contains(&path) {
+                files.push(path);
+            }
+        }
+    }
+
+    files
+}
+
+/// Values of `src="..."` attributes that point to local files, e.g. `<script src="rle.js">`.
+fn src_references(markdown: &str) -> Vec<&str> {
+    markdown
+        .match_indices("src=")
+        .filter_map(|(i, _)| {
+            let rest = &markdown[i + "src=".len()..];
+            let quote = rest.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+            let value = &rest[1..];
+            Some(&value[..value.find(quote)?])
+        })
+        .filter(|value| {
+            !value.contains("://") && !value.starts_with("//") && !value.starts_with("data:")
+        })
+        .collect()
+}